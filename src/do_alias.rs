@@ -1,5 +1,7 @@
 use std::{env, process, io};
+use std::io::{Error, ErrorKind};
 
+use serde_json::json;
 use clap::{
     self,
     App, Arg, SubCommand,
@@ -27,17 +29,47 @@ fn try_run(args: &Vec<String>) -> io::Result<()> {
         return Ok(());
     }
 
-    if let Some(ref matches) = matches.subcommand_matches("edit") {
+    if let Some(matches) = matches.subcommand_matches("edit") {
         edit(matches)?;
     }
-    if let Some(ref matches) = matches.subcommand_matches("remove") {
+    if let Some(matches) = matches.subcommand_matches("remove") {
         remove(matches)?;
     }
-    if let Some(ref matches) = matches.subcommand_matches("list") {
+    if let Some(matches) = matches.subcommand_matches("rename") {
+        rename(matches)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("copy") {
+        copy(matches)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("list") {
         list(matches)?;
     }
-    if let Some(ref _matches) = matches.subcommand_matches("repair") {
-        repair()?;
+    if let Some(matches) = matches.subcommand_matches("show") {
+        show(matches)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("which") {
+        which(matches)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("search") {
+        search(matches)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("repair") {
+        repair(matches)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("doctor") {
+        doctor(matches)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("export") {
+        export(matches)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("import") {
+        import(matches)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("lint") {
+        lint(matches)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("test") {
+        test(matches)?;
     }
 
     Ok(())
@@ -46,12 +78,38 @@ fn try_run(args: &Vec<String>) -> io::Result<()> {
 fn edit(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
     if let Some(alias_name) = matches.value_of("alias_name") {
         lib::alias::validate(alias_name)?;
+        if ["description", "tag", "owner", "shell"].iter().any(|x| matches.is_present(x)) {
+            edit_meta(alias_name, matches)?;
+            return Ok(());
+        }
         lib::alias::edit(alias_name)?;
         lib::alias::mklink(alias_name)?;
     }
     Ok(())
 }
 
+// update metadata only, without editor
+fn edit_meta(alias_name: &str, matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    lib::exec::read(&lib::path::cfg_list_path()?, alias_name)?;
+
+    let mut meta = lib::meta::read_sidecar(alias_name)?;
+    if let Some(description) = matches.value_of("description") {
+        meta.description = Some(description.to_owned());
+    }
+    if let Some(tags) = matches.values_of("tag") {
+        meta.tags = tags.map(|x| x.to_owned()).collect();
+    }
+    if let Some(owner) = matches.value_of("owner") {
+        meta.owner = Some(owner.to_owned());
+    }
+    if let Some(shell) = matches.value_of("shell") {
+        meta.shell = Some(shell.to_owned());
+    }
+    lib::meta::write(alias_name, &meta)?;
+    println!("{} metadata updated", term::keywrite(alias_name)?);
+    Ok(())
+}
+
 fn remove(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
     if let Some(alias_name) = matches.value_of("alias_name") {
         lib::alias::remove(alias_name)?;
@@ -60,26 +118,574 @@ fn remove(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
     Ok(())
 }
 
+fn rename(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    if let (Some(old_name), Some(new_name)) = (matches.value_of("old_name"), matches.value_of("new_name")) {
+        lib::alias::rename(old_name, new_name, matches.is_present("force"))?;
+        println!("{} renamed to {}", term::keywrite(old_name)?, term::keywrite(new_name)?);
+    }
+    Ok(())
+}
+
+fn copy(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    if let (Some(src_name), Some(dst_name)) = (matches.value_of("src_name"), matches.value_of("dst_name")) {
+        lib::alias::copy(src_name, dst_name, matches.is_present("force"))?;
+        println!("{} copied to {}", term::keywrite(src_name)?, term::keywrite(dst_name)?);
+    }
+    Ok(())
+}
+
 fn list(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
-    if matches.is_present("key") {
-        for (key, _value) in lib::alias::list_iter()? {
-            println!("{}", term::keywrite(&key)?);
+    let format = matches.value_of("format").unwrap_or("text");
+    if format == "json" {
+        term::set_color(false);
+    }
+    warn_duplicates()?;
+
+    let tags: Vec<&str> = matches.values_of("tag").map(|x| x.collect()).unwrap_or_default();
+    let mut aliases = Vec::new();
+    for (key, value) in lib::alias::list_iter()? {
+        // broken one doesn't hide others
+        let meta = lib::meta::read(&key).unwrap_or_else(|err| {
+            eprintln!("{}\n", err);
+            lib::meta::Meta::default()
+        });
+        if tags.iter().all(|tag| meta.tags.iter().any(|x| x == tag)) {
+            aliases.push((key, value, meta));
         }
-    } else {
-        for (key, value) in lib::alias::list_iter()? {
-            print!("{}", term::keywrite(&key)?);
-            println!(":\n{}", value);
+    }
+    match matches.value_of("sort") {
+        Some("usage") => aliases.sort_by(|a, b| b.2.runs.cmp(&a.2.runs).then(a.0.cmp(&b.0))),
+        _ => aliases.sort_by(|a, b| a.0.cmp(&b.0)),
+    }
+
+    let long = matches.is_present("long");
+    match format {
+        "json" => {
+            let cfg_list_path = lib::path::cfg_list_path()?;
+            let mut json = Vec::new();
+            for (key, value, meta) in &aliases {
+                json.push(json!({
+                    "name": key,
+                    "body": value,
+                    "path": lib::exec::read(&cfg_list_path, key).ok().map(|x| x.file),
+                    "link": lib::alias::link_status(key).as_str(),
+                    "meta": meta,
+                }));
+            }
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        },
+        "table" => {
+            let name_width = aliases.iter().map(|x| x.0.chars().count()).max().unwrap_or(0).max("NAME".len());
+            let link_width = if long { "dangling".len() + 2 } else { 0 };
+            let body_width = term::width().saturating_sub(name_width + 2 + link_width + 1).max(10);
+
+            print!("{:<width$}  ", "NAME", width = name_width);
+            if long {
+                print!("{:<width$}", "LINK", width = link_width);
+            }
+            println!("BODY");
+            for (key, value, _meta) in &aliases {
+                print!("{}{}  ", term::keywrite(key)?, " ".repeat(name_width - key.chars().count()));
+                if long {
+                    print!("{:<width$}", lib::alias::link_status(key).as_str(), width = link_width);
+                }
+                println!("{}", preview(value, body_width));
+            }
+        },
+        _ => {
+            if matches.is_present("key") {
+                for (key, _value, _meta) in &aliases {
+                    print!("{}", term::keywrite(key)?);
+                    println!("{}", link_suffix(key, long)?);
+                }
+            } else if matches.is_present("describe") {
+                let width = aliases.iter().map(|x| x.0.chars().count()).max().unwrap_or(0);
+                for (key, _value, meta) in &aliases {
+                    print!("{}", term::keywrite(key)?);
+                    print!("{}  ", " ".repeat(width - key.chars().count()));
+                    print!("{}", meta.description.as_deref().unwrap_or("-"));
+                    if !meta.tags.is_empty() {
+                        print!(" [{}]", meta.tags.join(", "));
+                    }
+                    println!("{}", link_suffix(key, long)?);
+                }
+            } else {
+                for (key, value, _meta) in &aliases {
+                    print!("{}", term::keywrite(key)?);
+                    println!("{}:\n{}", link_suffix(key, long)?, value);
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+// body in one line, cut to width
+fn preview(value: &str, width: usize) -> String {
+    let line: Vec<&str> = value.lines().map(|x| x.trim()).filter(|x| !x.is_empty()).collect();
+    let line = line.join(" ; ");
+    if line.chars().count() <= width {
+        return line;
+    }
+    let cut: String = line.chars().take(width.saturating_sub(3)).collect();
+    format!("{}...", cut)
+}
+
+// " (dangling)" etc, with --long
+fn link_suffix(alias_name: &str, long: bool) -> io::Result<String> {
+    if !long {
+        return Ok(String::new());
+    }
+    match lib::alias::link_status(alias_name) {
+        lib::alias::LinkStatus::Ok => Ok(" (ok)".to_owned()),
+        status => Ok(format!(" ({})", term::colorwrite(status.as_str(), term::Color::Red)?)),
+    }
+}
+
+fn show(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    if let Some(alias_name) = matches.value_of("alias_name") {
+        let def = lib::exec::read(&lib::path::cfg_list_path()?, alias_name)?;
+
+        if matches.is_present("path") {
+            println!("{}", def.file);
+        } else if matches.is_present("raw") {
+            print!("{}", def.body);
+        } else {
+            println!("{}", lib::highlight::highlight(def.body.trim_end())?);
         }
     }
     Ok(())
 }
 
-fn repair() -> io::Result<()> {
+fn search(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    use lib::search::{self, Scope};
+
+    if let Some(pattern) = matches.value_of("pattern") {
+        let re = search::pattern(pattern, matches.is_present("fuzzy"))?;
+        let scope = if matches.is_present("name-only") {
+            Scope::Name
+        } else if matches.is_present("body-only") {
+            Scope::Body
+        } else {
+            Scope::All
+        };
+        let context: usize = matches.value_of("context").and_then(|x| x.parse().ok()).unwrap_or(1);
+
+        let found = search::search(&re, scope)?;
+        for (i, x) in found.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print!("{}", search::highlight(&x.alias_name, &x.name)?);
+            match x.description {
+                Some((ref text, ref ranges)) => println!(" - {}", search::highlight(text, ranges)?),
+                None => println!(),
+            }
+
+            // matched lines, with context like grep
+            let mut shown = std::collections::BTreeSet::new();
+            for &(line, _) in &x.lines {
+                shown.extend(line.saturating_sub(context)..(line + context + 1).min(x.body.len()));
+            }
+            let mut last = None;
+            for k in shown {
+                if last.map(|last| k > last + 1).unwrap_or(false) {
+                    println!("    --");
+                }
+                match x.lines.iter().find(|x| x.0 == k) {
+                    Some((_, ranges)) => println!("{:>4}: {}", k + 1, search::highlight(&x.body[k], ranges)?),
+                    None => println!("{:>4}- {}", k + 1, x.body[k]),
+                }
+                last = Some(k);
+            }
+        }
+
+        if found.is_empty() {
+            return Err(Error::new(ErrorKind::NotFound, format!("{}: no alias matches {}", term::ewrite("failed")?, pattern)));
+        }
+    }
+    Ok(())
+}
+
+fn which(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    if let Some(name) = matches.value_of("name") {
+        let list_dir = lib::path::alias_list_dir();
+        let builtin = lib::path::is_cmd_builtin(name);
+        let found = lib::path::which(name);
+
+        // candidates in order of precedence
+        let mut candidates = Vec::new();
+        if builtin {
+            candidates.push((format!("{} (cmd built-in)", name), false));
+        }
+        for x in &found {
+            let is_alias = x.parent().map(|dir| lib::path::same_path(dir, &list_dir)).unwrap_or(false);
+            candidates.push((x.display().to_string(), is_alias));
+        }
+        for (i, (candidate, is_alias)) in candidates.iter().enumerate() {
+            if *is_alias {
+                println!("{:>3}. {} ({})", i + 1, candidate, term::keywrite("alias")?);
+            } else {
+                println!("{:>3}. {}", i + 1, candidate);
+            }
+        }
+
+        match candidates.iter().position(|x| x.1) {
+            Some(i) if i > 0 => {
+                eprintln!("{}", lib::diag::warning(&format!("{}: alias is shadowed by {}", name, candidates[0].0))
+                    .hint("move the list directory earlier in PATH, or rename the alias"));
+            },
+            Some(_) => (),
+            None if lib::alias::exists(name)? => {
+                eprintln!("{}", lib::diag::warning(&format!("{}: alias is defined, but its link is not found on PATH", name))
+                    .hint(&format!("add {} to PATH, or run `alias doctor`", list_dir.display())));
+            },
+            None if candidates.is_empty() => {
+                return Err(Error::new(ErrorKind::NotFound, format!("{}: {} is not found", term::ewrite("failed")?, name)));
+            },
+            None => (),
+        }
+    }
+    Ok(())
+}
+
+fn repair(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    use lib::alias::Repaired;
+
+    warn_duplicates()?;
+
+    let dry_run = matches.is_present("dry-run");
+    let force = matches.is_present("force");
+    let (mut created, mut fixed, mut removed, mut unchanged, mut kept, mut skipped) = (0, 0, 0, 0, 0, 0);
     for (key, _value) in lib::alias::cfg_iter()? {
-        lib::alias::mklink(&key)?;
-        println!("{} repaired", term::keywrite(&key)?);
+        // others are still repaired
+        if let Err(err) = lib::alias::validate(&key) {
+            eprintln!("{}", err);
+            skipped += 1;
+            continue;
+        }
+        match lib::alias::repair(&key, dry_run, force)? {
+            Repaired::Created => {
+                println!("{} created", term::keywrite(&key)?);
+                created += 1;
+            },
+            Repaired::Fixed => {
+                println!("{} fixed", term::keywrite(&key)?);
+                fixed += 1;
+            },
+            Repaired::Unchanged => unchanged += 1,
+            Repaired::Kept => {
+                println!("{} kept, not a link to alias.exe (replace it with --force)", term::keywrite(&key)?);
+                kept += 1;
+            },
+        }
+    }
+
+    if matches.is_present("prune") {
+        let cfg_list_path = lib::path::cfg_list_path()?;
+        let keys: Vec<String> = lib::alias::list_iter()?.map(|(key, _)| key).collect();
+        for key in keys {
+            if lib::exec::read(&cfg_list_path, &key).is_err() {
+                if !dry_run {
+                    lib::alias::remove_link(&key)?;
+                }
+                println!("{} removed", term::keywrite(&key)?);
+                removed += 1;
+            }
+        }
     }
 
+    println!("\n{}{} created, {} fixed, {} removed, {} unchanged, {} kept, {} skipped",
+        if dry_run { "dry run: " } else { "" }, created, fixed, removed, unchanged, kept, skipped);
+    Ok(())
+}
+
+fn doctor(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    let problems = lib::doctor::check()?;
+    for problem in &problems {
+        eprintln!("{}\n", problem.diag());
+    }
+
+    let errors = problems.iter().filter(|x| x.diag().level() == lib::diag::Level::Error).count();
+    if matches.is_present("fix") {
+        let mut fixed = 0;
+        for problem in problems.iter().filter(|x| x.fixable()) {
+            if lib::doctor::fix(problem, confirm)? {
+                fixed += 1;
+            }
+        }
+        println!("{} problem(s) found, {} fixed", problems.len(), fixed);
+        return Ok(());
+    }
+
+    if problems.is_empty() {
+        println!("{}", term::keywrite("no problems found")?);
+    } else {
+        println!("{} error(s), {} warning(s)", errors, problems.len() - errors);
+    }
+    if errors > 0 {
+        return Err(Error::other(format!("{}: run `alias doctor --fix` to repair links", term::ewrite("doctor failed")?)));
+    }
+    Ok(())
+}
+
+fn confirm(question: &str) -> io::Result<bool> {
+    use std::io::Write;
+
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim().eq_ignore_ascii_case("y") || line.trim().eq_ignore_ascii_case("yes"))
+}
+
+fn warn_duplicates() -> io::Result<()> {
+    for diag in lib::alias::duplicates()? {
+        eprintln!("{}\n", diag);
+    }
+    Ok(())
+}
+
+fn export(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    let names: Vec<String> = matches.values_of("alias_names")
+        .map(|x| x.map(|x| x.to_owned()).collect())
+        .unwrap_or_default();
+
+    if let Some(format) = matches.value_of("format").and_then(lib::script::Format::parse) {
+        return export_script(format, &names, matches.value_of("output"));
+    }
+
+    let bundle = lib::bundle::export(&names)?;
+    let text = lib::bundle::to_string(&bundle)?;
+    if let Some(output) = matches.value_of("output") {
+        std::fs::write(output, text)?;
+        eprintln!("{} alias(es) exported to {}", bundle.alias.len(), term::keywrite(output)?);
+    } else {
+        print!("{}", text);
+    }
+    Ok(())
+}
+
+fn export_script(format: lib::script::Format, names: &[String], output: Option<&str>) -> io::Result<()> {
+    let bundle = lib::bundle::export(names)?;
+    let multiple = bundle.alias.len() > 1;
+    if let (true, Some(output)) = (multiple, output) {
+        std::fs::create_dir_all(output)?;
+    }
+
+    let cfg_list_path = lib::path::cfg_list_path()?;
+    let mut failed = 0;
+    for entry in &bundle.alias {
+        let def = lib::exec::read(&cfg_list_path, &entry.name)?;
+        let script = match lib::script::compile(format, &def) {
+            Ok(script) => script,
+            Err(err) => {
+                eprintln!("{}\n", err);
+                failed += 1;
+                continue;
+            },
+        };
+
+        match output {
+            Some(output) => {
+                let path = if multiple {
+                    format!("{}/{}.{}", output, entry.name, format.ext())
+                } else {
+                    output.to_owned()
+                };
+                std::fs::write(&path, script)?;
+                eprintln!("{} exported to {}", term::keywrite(&entry.name)?, term::keywrite(&path)?);
+            },
+            None => print!("{}", script),
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{}: {} alias(es) can't be translated", term::ewrite("failed")?, failed)));
+    }
+    Ok(())
+}
+
+fn import(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    use lib::bundle::{Conflict, Imported};
+
+    if let Some(file) = matches.value_of("file") {
+        let bundle = match matches.value_of("from") {
+            Some(format) => from_shell(format, file)?,
+            None => lib::bundle::read(file)?,
+        };
+
+        let conflict = if matches.is_present("skip") {
+            Some(Conflict::Skip)
+        } else if matches.is_present("overwrite") {
+            Some(Conflict::Overwrite)
+        } else if matches.is_present("rename") {
+            Some(Conflict::Rename)
+        } else {
+            None
+        };
+
+        let imported = lib::bundle::import(&bundle, |alias_name| {
+            match conflict {
+                Some(conflict) => Ok(conflict),
+                None => prompt_conflict(alias_name),
+            }
+        })?;
+
+        for x in imported {
+            match x {
+                Imported::Created(name) => {
+                    lib::alias::mklink(&name)?;
+                    println!("{} imported", term::keywrite(&name)?);
+                },
+                Imported::Overwritten(name) => {
+                    lib::alias::mklink(&name)?;
+                    println!("{} overwritten", term::keywrite(&name)?);
+                },
+                Imported::Renamed(name, new_name) => {
+                    lib::alias::mklink(&new_name)?;
+                    println!("{} imported as {}", term::keywrite(&name)?, term::keywrite(&new_name)?);
+                },
+                Imported::Skipped(name) => println!("{} skipped", term::keywrite(&name)?),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn from_shell(format: &str, file: &str) -> io::Result<lib::bundle::Bundle> {
+    use lib::convert::Converted;
+
+    let text = lib::encode::to_utf8_string(&std::fs::read(file)?);
+
+    let mut entries = Vec::new();
+    for x in lib::convert::convert(format, file, &text)? {
+        match x {
+            Converted::Alias(name, body) => entries.push(lib::bundle::Entry {
+                name,
+                body,
+                description: None,
+                tags: Vec::new(),
+                test: None,
+                snapshots: Default::default(),
+            }),
+            Converted::Failed(diag) => eprintln!("{}\n", diag),
+        }
+    }
+    Ok(lib::bundle::Bundle { version: lib::bundle::VERSION, alias: entries })
+}
+
+fn prompt_conflict(alias_name: &str) -> io::Result<lib::bundle::Conflict> {
+    use std::io::Write;
+    use lib::bundle::Conflict;
+
+    loop {
+        print!("{} already exists, [s]kip, [o]verwrite or [r]ename? ", term::keywrite(alias_name)?);
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(Conflict::Skip);
+        }
+        match line.trim() {
+            "s" | "skip" => return Ok(Conflict::Skip),
+            "o" | "overwrite" => return Ok(Conflict::Overwrite),
+            "r" | "rename" => return Ok(Conflict::Rename),
+            _ => (),
+        }
+    }
+}
+
+fn lint(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    let cfg_list_path = lib::path::cfg_list_path()?;
+
+    let mut lints = Vec::new();
+    if let Some(alias_name) = matches.value_of("alias_name") {
+        let def = lib::exec::read(&cfg_list_path, alias_name)?;
+        lints.append(&mut lib::lint::lint(alias_name, &def)?);
+    } else {
+        for (key, _value) in lib::alias::cfg_iter()? {
+            let def = lib::exec::read(&cfg_list_path, &key)?;
+            lints.append(&mut lib::lint::lint(&key, &def)?);
+        }
+    }
+
+    if matches.is_present("json") {
+        let json: Vec<serde_json::Value> = lints.iter()
+            .map(|x| {
+                let pos = x.diag.position();
+                json!({
+                    "alias": x.alias_name,
+                    "file": x.diag.file(),
+                    "line": pos.map(|x| x.0),
+                    "column": pos.map(|x| x.1),
+                    "level": x.diag.level().as_str(),
+                    "code": x.code,
+                    "message": x.diag.message(),
+                    "hint": x.diag.hint_text(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+    } else {
+        for x in &lints {
+            eprintln!("{}\n", x.diag);
+        }
+    }
+
+    let errors = lints.iter().filter(|x| x.diag.level() == lib::diag::Level::Error).count();
+    if errors > 0 {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{}: {} error(s), {} warning(s)", term::ewrite("lint failed")?, errors, lints.len() - errors)));
+    }
+    Ok(())
+}
+
+fn test(matches: &clap::ArgMatches<'static>) -> io::Result<()> {
+    let cfg_list_path = lib::path::cfg_list_path()?;
+    let update = matches.is_present("update");
+
+    let mut names = Vec::new();
+    if let Some(alias_name) = matches.value_of("alias_name") {
+        names.push(alias_name.to_owned());
+    } else {
+        for (key, _value) in lib::alias::cfg_iter()? {
+            if std::path::Path::new(&lib::spec::spec_path(&cfg_list_path, &key)).exists() {
+                names.push(key);
+            }
+        }
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+    for alias_name in names {
+        let spec_toml = lib::spec::spec_path(&cfg_list_path, &alias_name);
+        if !std::path::Path::new(&spec_toml).exists() {
+            return Err(Error::new(ErrorKind::NotFound, format!("{}: {}.test.toml is not found", term::ewrite("failed")?, alias_name)));
+        }
+        let snap_dir = lib::spec::snap_path(&cfg_list_path, &alias_name);
+
+        for case in lib::spec::read(&spec_toml)?.case {
+            let output = lib::spec::run(&alias_name, &case)?;
+            let failures = lib::spec::check(&case, &output, &snap_dir, update)?;
+
+            let name = format!("{}::{}", alias_name, case.name);
+            if failures.is_empty() {
+                println!("test {} ... ok", term::keywrite(&name)?);
+                passed += 1;
+            } else {
+                println!("test {} ... {}", term::keywrite(&name)?, term::ewrite("FAILED")?);
+                for failure in failures {
+                    println!("    {}", failure.replace("\n", "\n    "));
+                }
+                failed += 1;
+            }
+        }
+    }
+
+    println!("\ntest result: {} passed; {} failed", passed, failed);
+    if failed > 0 {
+        return Err(Error::other(format!("{}: {} test(s)", term::ewrite("failed")?, failed)));
+    }
     Ok(())
 }
 
@@ -92,8 +698,18 @@ SUBCOMMAND:
     help    Prints help information
     edit    Edit alias, new or existing
     remove  Remove alias
+    rename  Rename alias
+    copy    Copy alias
     list    List aliases
-    repair  Repair aliases from .txt";
+    show    Show alias
+    search  Search aliases by name, description and body
+    which   Show which command runs for name, and shadowing
+    repair  Repair aliases from .txt and aliases.toml
+    doctor  Diagnose installation and PATH problems
+    export  Export aliases to bundle
+    import  Import aliases from bundle
+    lint    Check aliases without executing
+    test    Run test cases from .test.toml";
 
 const TEMPLATE: &str = "\
 {bin} {version}
@@ -114,17 +730,94 @@ fn clap_matches(args: &Vec<String>) -> clap::ArgMatches<'static> {
         .subcommand(
             SubCommand::with_name("edit")
                 .arg(Arg::from_usage("<alias_name> 'alias exe name'"))
+                .arg(Arg::from_usage("-d --description=[text] 'Set description, without editor'"))
+                .arg(Arg::from_usage("-t --tag=[tag]... 'Set tags, without editor'").number_of_values(1))
+                .arg(Arg::from_usage("--owner=[owner] 'Set owner, without editor'"))
+                .arg(Arg::from_usage("--shell=[shell] 'Set shell backend, without editor'"))
         )
         .subcommand(
             SubCommand::with_name("remove")
                 .arg(Arg::from_usage("<alias_name> 'alias exe name'"))
         )
+        .subcommand(
+            SubCommand::with_name("rename")
+                .arg(Arg::from_usage("<old_name> 'alias exe name'"))
+                .arg(Arg::from_usage("<new_name> 'new alias exe name'"))
+                .arg(Arg::from_usage("-f --force 'Overwrite existing alias'"))
+        )
+        .subcommand(
+            SubCommand::with_name("copy")
+                .arg(Arg::from_usage("<src_name> 'alias exe name'"))
+                .arg(Arg::from_usage("<dst_name> 'new alias exe name'"))
+                .arg(Arg::from_usage("-f --force 'Overwrite existing alias'"))
+        )
         .subcommand(
             SubCommand::with_name("list")
                 .arg(Arg::from_usage("-k --key 'List up only key'"))
+                .arg(Arg::from_usage("-d --describe 'List up key and description'").conflicts_with("key"))
+                .arg(Arg::from_usage("-l --long 'Show link status'"))
+                .arg(Arg::from_usage("--format=[format] 'Output format'")
+                    .possible_values(&["text", "table", "json"])
+                    .default_value("text"))
+                .arg(Arg::from_usage("-t --tag=[tag]... 'Only aliases with tag'").number_of_values(1))
+                .arg(Arg::from_usage("--sort=[key] 'Sort by'")
+                    .possible_values(&["name", "usage"])
+                    .default_value("name"))
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .arg(Arg::from_usage("<alias_name> 'alias exe name'"))
+                .arg(Arg::from_usage("--raw 'Show without highlighting'"))
+                .arg(Arg::from_usage("--path 'Show .txt file location'"))
+        )
+        .subcommand(
+            SubCommand::with_name("search")
+                .arg(Arg::from_usage("<pattern> 'regex, case insensitive'"))
+                .arg(Arg::from_usage("-f --fuzzy 'Match characters in order, e.g. gst for git status'"))
+                .arg(Arg::from_usage("-n --name-only 'Search names only'").conflicts_with("body-only"))
+                .arg(Arg::from_usage("-b --body-only 'Search bodies only'"))
+                .arg(Arg::from_usage("-C --context=[lines] 'Lines of context around matched line'").default_value("1"))
+        )
+        .subcommand(
+            SubCommand::with_name("which")
+                .arg(Arg::from_usage("<name> 'command name'"))
         )
         .subcommand(
             SubCommand::with_name("repair")
+                .arg(Arg::from_usage("--prune 'Remove links without .txt'"))
+                .arg(Arg::from_usage("-n --dry-run 'Show what would be done'"))
+                .arg(Arg::from_usage("-f --force 'Replace exe in list which is not a link to alias.exe'"))
+        )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .arg(Arg::from_usage("--fix 'Create and fix links, remove orphans after confirmation'"))
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .arg(Arg::from_usage("[alias_names]... 'alias exe names, all aliases if omitted'"))
+                .arg(Arg::from_usage("-o --output=[bundle] 'Output file, stdout if omitted (directory for multiple scripts)'"))
+                .arg(Arg::from_usage("--format=[format] 'Output format, bundle or standalone script'")
+                    .possible_values(&["toml", "sh", "bash", "cmd", "ps1"])
+                    .default_value("toml"))
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .arg(Arg::from_usage("<file> 'bundle file, or alias definitions with --from'"))
+                .arg(Arg::from_usage("--from=[format] 'Import from alias definitions of other shell'")
+                    .possible_values(&["bash", "zsh", "doskey", "powershell"]))
+                .arg(Arg::from_usage("--skip 'Skip existing aliases'").conflicts_with_all(&["overwrite", "rename"]))
+                .arg(Arg::from_usage("--overwrite 'Overwrite existing aliases'").conflicts_with("rename"))
+                .arg(Arg::from_usage("--rename 'Import existing aliases as <name>-<n>'"))
+        )
+        .subcommand(
+            SubCommand::with_name("lint")
+                .arg(Arg::from_usage("[alias_name] 'alias exe name, all aliases if omitted'"))
+                .arg(Arg::from_usage("--json 'Output as JSON'"))
+        )
+        .subcommand(
+            SubCommand::with_name("test")
+                .arg(Arg::from_usage("[alias_name] 'alias exe name, all tested aliases if omitted'"))
+                .arg(Arg::from_usage("--update 'Rewrite snapshots'"))
         )
         .get_matches_from(args)
 }
@@ -8,19 +8,36 @@ pub fn run(args: &Vec<String>) {
         .and_then(|x| x.to_str())
         .unwrap();
 
+    exec(alias_name, args, true);
+}
+
+// test case started by `alias test`, args[0] is alias.exe
+pub fn run_test(alias_name: &str, args: &[String]) {
+    let mut args = args.to_vec();
+    args[0] = alias_name.to_owned();
+
+    exec(alias_name, &args, false);
+}
+
+fn exec(alias_name: &str, args: &Vec<String>, record: bool) {
     let cfg_list_path = lib::path::cfg_list_path();
     if let Err(err) = cfg_list_path {
         eprintln!("{}", err);
         process::exit(1);
     };
 
-    let value = lib::exec::read(&cfg_list_path.unwrap(), alias_name);
-    if let Err(err) = value {
+    let def = lib::exec::read(&cfg_list_path.unwrap(), alias_name);
+    if let Err(err) = def {
         eprintln!("{}", err);
         process::exit(1);
     }
 
-    match lib::exec::run(&value.unwrap(), args) {
+    // usage statistics, best effort
+    if record {
+        let _ = lib::meta::record_run(alias_name);
+    }
+
+    match lib::exec::run(&def.unwrap(), args) {
         Ok(status_code) => process::exit(status_code),
         Err(err) => {
             eprintln!("{}", err);
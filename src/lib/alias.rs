@@ -1,16 +1,22 @@
 use std::{env, fs, path::Path};
+use std::collections::HashSet;
 use std::io::{self, Read, Error, ErrorKind};
 use std::process::{Child, Command};
 
 use crate::lib::path::{self, LISTDIR};
+use crate::lib::config;
+use crate::lib::diag::{self, Diagnostic};
 use crate::lib::encode;
+use crate::lib::meta;
+use crate::lib::spec;
 use crate::lib::term;
 
 // -----
 
 pub struct AliasListIterator {
     dir: io::Result<fs::ReadDir>,
-    list_path: String
+    list_path: String,
+    toml: Vec<config::Entry>,
 }
 
 impl Iterator for AliasListIterator {
@@ -29,6 +35,8 @@ impl Iterator for AliasListIterator {
                                 .map(|x| x.to_string())
                                 .unwrap();
                             let value = fs::read_to_string(format!("{}/{}.txt", self.list_path, alias_name))
+                                .ok()
+                                .or_else(|| self.toml.iter().find(|x| x.name == alias_name).map(|x| x.body.clone()))
                                 .map(|x| x.trim().to_string())
                                 .unwrap_or("".to_owned());
                             return Some((alias_name, value));
@@ -44,17 +52,22 @@ impl Iterator for AliasListIterator {
 }
 
 pub fn list_iter() -> io::Result<AliasListIterator> {
-    Ok(AliasListIterator { dir: fs::read_dir(LISTDIR), list_path: path::cfg_list_path()? })
+    Ok(AliasListIterator { dir: fs::read_dir(LISTDIR), list_path: path::cfg_list_path()?, toml: config::read()? })
 }
 
 // -----
 
 pub struct ConfigPathListIterator {
     dir: io::Result<fs::ReadDir>,
+    list_path: String,
+    // aliases.toml entries, after .txt files
+    toml: std::vec::IntoIter<config::Entry>,
+    toml_path: String,
+    seen: HashSet<String>,
 }
 
 impl Iterator for ConfigPathListIterator {
-    // type Item = (<alias_name>, <cfg_path>)
+    // type Item = (<alias_name>, <cfg_path>), cfg_path is .txt or aliases.toml
     type Item = (String, String);
 
     fn next(&mut self) -> Option<(String, String)> {
@@ -80,12 +93,50 @@ impl Iterator for ConfigPathListIterator {
             }
         }
 
+        // .txt takes precedence, and first one wins in aliases.toml
+        for entry in self.toml.by_ref() {
+            if Path::new(&format!("{}/{}.txt", self.list_path, entry.name)).exists() || !self.seen.insert(entry.name.clone()) {
+                continue;
+            }
+            return Some((entry.name, self.toml_path.clone()));
+        }
+
         None
     }
 }
 
 pub fn cfg_iter() -> io::Result<ConfigPathListIterator> {
-    Ok(ConfigPathListIterator { dir: fs::read_dir(path::cfg_list_path()?) })
+    let list_path = path::cfg_list_path()?;
+    Ok(ConfigPathListIterator {
+        dir: fs::read_dir(&list_path),
+        list_path,
+        toml: config::read()?.into_iter(),
+        toml_path: config::toml_path()?,
+        seen: HashSet::new(),
+    })
+}
+
+// aliases defined more than once, ignored ones
+pub fn duplicates() -> io::Result<Vec<Diagnostic>> {
+    let list_path = path::cfg_list_path()?;
+    let toml_path = config::toml_path()?;
+
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+    for entry in config::read()? {
+        let alias_txt = format!("{}/{}.txt", list_path, entry.name);
+        let message = if Path::new(&alias_txt).exists() {
+            format!("{} is also defined in {}", entry.name, alias_txt)
+        } else if !seen.insert(entry.name.clone()) {
+            format!("{} is defined more than once", entry.name)
+        } else {
+            continue;
+        };
+        duplicates.push(diag::warning(&message)
+            .hint(".txt takes precedence over aliases.toml, and the first definition wins in aliases.toml")
+            .locate(&toml_path, entry.line, &entry.body));
+    }
+    Ok(duplicates)
 }
 
 // -----
@@ -99,29 +150,37 @@ pub fn validate(alias_name: &str) -> io::Result<()> {
     if alias_name.eq(&orig_name) {
         return Err(Error::new(ErrorKind::InvalidData, format!("{}: original name is invalid: {}", term::ewrite("failed")?, term::ewrite(&orig_name)?)));
     }
+    if alias_name.is_empty() || alias_name.starts_with('.') || alias_name.contains(|c| "\\/:*?\"<>|".contains(c)) {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{}: illegal alias name: {}", term::ewrite("failed")?, term::ewrite(alias_name)?)));
+    }
     Ok(())
 }
 
 // -----
 
 pub fn edit(alias_name: &str) -> io::Result<()> {
-    let cfg_path = path::cfg_path()?;
-    if !Path::new(&cfg_path).exists() {
-        fs::create_dir(&cfg_path)?;
-    }
-    
-    let cfg_list_path = path::cfg_list_path()?;
-    if !Path::new(&cfg_list_path).exists() {
-        fs::create_dir(&cfg_list_path)?;
-    }
+    let cfg_list_path = create_cfg_list_path()?;
 
-    let alias_txt = format!("{}/{}.txt", &cfg_list_path, alias_name);
+    let mut alias_txt = format!("{}/{}.txt", &cfg_list_path, alias_name);
+    let mut created = false;
     if !Path::new(&alias_txt).exists() {
-        fs::File::create(&alias_txt)?;
+        if config::read()?.iter().any(|x| x.name == alias_name) {
+            // defined in aliases.toml, edit there
+            alias_txt = config::toml_path()?;
+        } else {
+            fs::File::create(&alias_txt)?;
+            created = true;
+        }
     }
+    let modified = fs::metadata(&alias_txt)?.modified()?;
 
     try_edit("vim", &alias_txt)
-        .or_else(|_| try_edit("notepad", &alias_txt))
+        .or_else(|_| try_edit("notepad", &alias_txt))?;
+
+    if created || fs::metadata(&alias_txt)?.modified()? != modified {
+        meta::touch(alias_name)?;
+    }
+    Ok(())
 }
 fn try_edit(editor: &str, alias_txt: &str) -> io::Result<()> {
     let cmd = Command::new(editor)
@@ -145,6 +204,106 @@ fn try_edit(editor: &str, alias_txt: &str) -> io::Result<()> {
 
 // -----
 
+pub fn write(alias_name: &str, value: &str) -> io::Result<()> {
+    let cfg_list_path = create_cfg_list_path()?;
+    fs::write(format!("{}/{}.txt", &cfg_list_path, alias_name), value)?;
+    meta::touch(alias_name)
+}
+
+pub fn exists(alias_name: &str) -> io::Result<bool> {
+    Ok(Path::new(&format!("{}/{}.txt", path::cfg_list_path()?, alias_name)).exists()
+        || config::read()?.iter().any(|x| x.name == alias_name))
+}
+
+fn create_cfg_list_path() -> io::Result<String> {
+    let cfg_path = path::cfg_path()?;
+    if !Path::new(&cfg_path).exists() {
+        fs::create_dir(&cfg_path)?;
+    }
+
+    let cfg_list_path = path::cfg_list_path()?;
+    if !Path::new(&cfg_list_path).exists() {
+        fs::create_dir(&cfg_list_path)?;
+    }
+    Ok(cfg_list_path)
+}
+
+// -----
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LinkStatus {
+    Ok,
+    Missing,
+    // target doesn't exist
+    Dangling,
+    // not a link to current alias.exe
+    Wrong,
+}
+
+impl LinkStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkStatus::Ok => "ok",
+            LinkStatus::Missing => "missing",
+            LinkStatus::Dangling => "dangling",
+            LinkStatus::Wrong => "wrong",
+        }
+    }
+}
+
+pub fn link_status(alias_name: &str) -> LinkStatus {
+    let alias_exe = Path::new(LISTDIR).join(format!("{}.exe", alias_name));
+    if fs::symlink_metadata(&alias_exe).is_err() {
+        return LinkStatus::Missing;
+    }
+    let target = match fs::read_link(&alias_exe) {
+        Ok(target) => Path::new(LISTDIR).join(target),
+        Err(_) => return LinkStatus::Wrong,
+    };
+    if !target.exists() {
+        return LinkStatus::Dangling;
+    }
+
+    let current_exe = env::current_exe().unwrap();
+    if path::same_path(&target, &current_exe) { LinkStatus::Ok } else { LinkStatus::Wrong }
+}
+
+pub enum Repaired {
+    Created,
+    Fixed,
+    Unchanged,
+    // not a link to alias.exe, and not forced
+    Kept,
+}
+
+// make link point at current alias.exe
+//
+// force: replace <alias_name>.exe which isn't a link to alias.exe,
+//        it may be a real executable put by user
+pub fn repair(alias_name: &str, dry_run: bool, force: bool) -> io::Result<Repaired> {
+    match link_status(alias_name) {
+        LinkStatus::Ok => Ok(Repaired::Unchanged),
+        LinkStatus::Missing => {
+            if !dry_run {
+                mklink(alias_name)?;
+            }
+            Ok(Repaired::Created)
+        },
+        LinkStatus::Wrong if !force => Ok(Repaired::Kept),
+        LinkStatus::Dangling | LinkStatus::Wrong => {
+            if !dry_run {
+                remove_link(alias_name)?;
+                mklink(alias_name)?;
+            }
+            Ok(Repaired::Fixed)
+        },
+    }
+}
+
+pub fn remove_link(alias_name: &str) -> io::Result<()> {
+    fs::remove_file(format!("{}/{}.exe", LISTDIR, alias_name))
+}
+
 pub fn mklink(alias_name: &str) -> io::Result<()> {
     let alias_exe = format!("{}/{}.exe", LISTDIR, alias_name);
     if Path::new(&alias_exe).exists() {
@@ -186,6 +345,8 @@ fn try_mklink(link: &str, target: &str) -> io::Result<()> {
 // -----
 
 pub fn remove(alias_name: &str) -> io::Result<()> {
+    in_txt(alias_name, "remove")?;
+
     let alias_exe = format!("{}/{}.exe", LISTDIR, alias_name);
     if !Path::new(&alias_exe).exists() {
         return Err(Error::new(ErrorKind::NotFound, format!("{}: {}.exe is not found", term::ewrite("failed")?, alias_name)));
@@ -193,10 +354,133 @@ pub fn remove(alias_name: &str) -> io::Result<()> {
 
     fs::remove_file(&alias_exe)?;
 
-    let alias_txt = format!("{}/{}.txt", path::cfg_list_path()?, alias_name);
-    if Path::new(&alias_txt).exists() {
-        fs::remove_file(&alias_txt)?;
+    let cfg_list_path = path::cfg_list_path()?;
+    let txt = format!("{}/{}.txt", cfg_list_path, alias_name);
+    if Path::new(&txt).exists() {
+        fs::remove_file(&txt)?;
+    }
+    // not to be inherited by a new alias of the same name
+    for (file, _) in companion_paths(&cfg_list_path, alias_name, alias_name) {
+        if Path::new(&file).is_dir() {
+            fs::remove_dir_all(&file)?;
+        } else if Path::new(&file).exists() {
+            fs::remove_file(&file)?;
+        }
+    }
+
+    Ok(())
+}
+
+// -----
+
+pub fn rename(old_name: &str, new_name: &str, force: bool) -> io::Result<()> {
+    in_txt(old_name, "rename")?;
+    let cfg_list_path = prepare_move(old_name, new_name, force)?;
+
+    fs::rename(format!("{}/{}.txt", cfg_list_path, old_name), format!("{}/{}.txt", cfg_list_path, new_name))?;
+    for (old, new) in companion_paths(&cfg_list_path, old_name, new_name) {
+        if Path::new(&old).exists() {
+            fs::rename(&old, &new)?;
+        }
+    }
+
+    let old_exe = format!("{}/{}.exe", LISTDIR, old_name);
+    if Path::new(&old_exe).exists() {
+        fs::remove_file(&old_exe)?;
+    }
+
+    mklink(new_name)
+}
+
+// alias in aliases.toml is copied into <dst_name>.txt
+pub fn copy(src_name: &str, dst_name: &str, force: bool) -> io::Result<()> {
+    let cfg_list_path = prepare_move(src_name, dst_name, force)?;
+
+    let src_txt = format!("{}/{}.txt", cfg_list_path, src_name);
+    let dst_txt = format!("{}/{}.txt", cfg_list_path, dst_name);
+    match config::read()?.into_iter().find(|x| x.name == src_name) {
+        Some(entry) if !Path::new(&src_txt).exists() => fs::write(&dst_txt, entry.body)?,
+        _ => { fs::copy(&src_txt, &dst_txt)?; },
+    }
+    for (src, dst) in companion_paths(&cfg_list_path, src_name, dst_name) {
+        let src = Path::new(&src);
+        if src.is_dir() {
+            fs::create_dir_all(&dst)?;
+            for entry in fs::read_dir(src)? {
+                let entry = entry?;
+                fs::copy(entry.path(), Path::new(&dst).join(entry.file_name()))?;
+            }
+        } else if src.exists() {
+            fs::copy(src, &dst)?;
+        }
+    }
+
+    // new alias, not the history of source
+    let mut meta = meta::read_sidecar(dst_name)?;
+    meta.created = None;
+    meta.updated = None;
+    meta.owner = None;
+    meta.runs = 0;
+    meta.last_run = None;
+    meta::write(dst_name, &meta)?;
+    meta::touch(dst_name)?;
+
+    mklink(dst_name)
+}
+
+// check names, and clear destination if forced
+fn prepare_move(src_name: &str, dst_name: &str, force: bool) -> io::Result<String> {
+    validate(dst_name)?;
+
+    let cfg_list_path = path::cfg_list_path()?;
+    if !exists(src_name)? {
+        return Err(Error::new(ErrorKind::NotFound, format!("{}: {}.txt is not found", term::ewrite("failed")?, src_name)));
+    }
+    if src_name == dst_name {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("{}: same name: {}", term::ewrite("failed")?, term::ewrite(dst_name)?)));
+    }
+    // .txt would shadow it silently
+    if config::read()?.iter().any(|x| x.name == dst_name) {
+        return Err(Error::new(ErrorKind::AlreadyExists, format!("{}: {} is defined in {}", term::ewrite("failed")?, term::ewrite(dst_name)?, config::toml_path()?)));
     }
 
+    let dst_txt = format!("{}/{}.txt", cfg_list_path, dst_name);
+    let dst_exe = format!("{}/{}.exe", LISTDIR, dst_name);
+    if Path::new(&dst_txt).exists() || Path::new(&dst_exe).exists() {
+        if !force {
+            return Err(Error::new(ErrorKind::AlreadyExists, format!("{}: {} already exists, use --force to overwrite", term::ewrite("failed")?, term::ewrite(dst_name)?)));
+        }
+        if Path::new(&dst_exe).exists() {
+            fs::remove_file(&dst_exe)?;
+        }
+        for (_, dst) in companion_paths(&cfg_list_path, src_name, dst_name) {
+            if Path::new(&dst).is_dir() {
+                fs::remove_dir_all(&dst)?;
+            } else if Path::new(&dst).exists() {
+                fs::remove_file(&dst)?;
+            }
+        }
+    }
+
+    Ok(cfg_list_path)
+}
+
+// alias in aliases.toml is edited by hand, not by alias.exe
+fn in_txt(alias_name: &str, action: &str) -> io::Result<()> {
+    let alias_txt = format!("{}/{}.txt", path::cfg_list_path()?, alias_name);
+    if !Path::new(&alias_txt).exists() && config::read()?.iter().any(|x| x.name == alias_name) {
+        return Err(diag::error(&format!("{} is defined in {}", alias_name, config::toml_path()?))
+            .hint(&format!("{} it in aliases.toml by hand", action))
+            .into_error());
+    }
     Ok(())
 }
+
+// files following alias .txt, e.g. test spec
+fn companion_paths(cfg_list_path: &str, src_name: &str, dst_name: &str) -> Vec<(String, String)> {
+    vec![
+        (spec::spec_path(cfg_list_path, src_name), spec::spec_path(cfg_list_path, dst_name)),
+        (spec::snap_path(cfg_list_path, src_name), spec::snap_path(cfg_list_path, dst_name)),
+        (meta::meta_path(cfg_list_path, src_name), meta::meta_path(cfg_list_path, dst_name)),
+    ]
+}
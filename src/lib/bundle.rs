@@ -0,0 +1,196 @@
+use std::fs;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::io::{self, Error, ErrorKind};
+
+use serde::{Serialize, Deserialize};
+
+use crate::lib::path;
+use crate::lib::alias;
+use crate::lib::exec;
+use crate::lib::meta;
+use crate::lib::spec;
+use crate::lib::term;
+
+pub const VERSION: u32 = 1;
+
+// alias bundle file(TOML)
+//
+//   version = 1
+//
+//   [[alias]]
+//   name = "hello"
+//   body = "echo hello $(echo world)"
+//   description = "..."          # <name>.meta.toml, if any
+//   tags = ["..."]
+//   test = "..."                 # <name>.test.toml, if exists
+//   [alias.snapshots]
+//   "greet.stdout" = "..."       # <name>.snap/*, if exists
+
+#[derive(Serialize, Deserialize)]
+pub struct Bundle {
+    pub version: u32,
+    #[serde(default)]
+    pub alias: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub test: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub snapshots: BTreeMap<String, String>,
+}
+
+// names: all aliases if empty
+pub fn export(names: &[String]) -> io::Result<Bundle> {
+    let cfg_list_path = path::cfg_list_path()?;
+
+    let mut names = names.to_vec();
+    if names.is_empty() {
+        names = alias::cfg_iter()?.map(|(key, _)| key).collect();
+        names.sort();
+    }
+
+    let mut entries = Vec::new();
+    for name in names {
+        let def = exec::read(&cfg_list_path, &name)?;
+
+        let spec_toml = spec::spec_path(&cfg_list_path, &name);
+        let test = if Path::new(&spec_toml).exists() { Some(fs::read_to_string(&spec_toml)?) } else { None };
+
+        let mut snapshots = BTreeMap::new();
+        let snap_dir = spec::snap_path(&cfg_list_path, &name);
+        if Path::new(&snap_dir).is_dir() {
+            for entry in fs::read_dir(&snap_dir)? {
+                let entry = entry?;
+                snapshots.insert(entry.file_name().to_string_lossy().into_owned(), fs::read_to_string(entry.path())?);
+            }
+        }
+
+        let meta = meta::read(&name)?;
+        entries.push(Entry { name: name.clone(), body: def.body, description: meta.description, tags: meta.tags, test, snapshots });
+    }
+
+    Ok(Bundle { version: VERSION, alias: entries })
+}
+
+pub fn to_string(bundle: &Bundle) -> io::Result<String> {
+    match toml::to_string(bundle) {
+        Ok(s) => Ok(s),
+        Err(err) => Err(Error::new(ErrorKind::InvalidData, format!("{}: {}", term::ewrite("failed")?, err))),
+    }
+}
+
+pub fn read(bundle_path: &str) -> io::Result<Bundle> {
+    let text = fs::read_to_string(bundle_path)?;
+    let bundle: Bundle = match toml::from_str(&text) {
+        Ok(bundle) => bundle,
+        Err(err) => return Err(Error::new(ErrorKind::InvalidData, format!("{}: {}: {}", term::ewrite("failed")?, bundle_path, err))),
+    };
+    if bundle.version > VERSION {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{}: {}: unsupported bundle version {}", term::ewrite("failed")?, bundle_path, bundle.version)));
+    }
+    Ok(bundle)
+}
+
+// -----
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Conflict {
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+pub enum Imported {
+    Created(String),
+    Overwritten(String),
+    // (<original name>, <new name>)
+    Renamed(String, String),
+    Skipped(String),
+}
+
+// fconflict: decides how to resolve an alias that already exists
+pub fn import(bundle: &Bundle, fconflict: impl Fn(&str) -> io::Result<Conflict>) -> io::Result<Vec<Imported>> {
+    // nothing is written if any entry is bad
+    for entry in &bundle.alias {
+        alias::validate(&entry.name)?;
+        for file in entry.snapshots.keys() {
+            if file.contains(['/', '\\']) || file.starts_with('.') {
+                return Err(Error::new(ErrorKind::InvalidData, format!("{}: {}: illegal snapshot name: {}", term::ewrite("failed")?, entry.name, file)));
+            }
+        }
+    }
+
+    let mut imported = Vec::new();
+    for entry in &bundle.alias {
+        if !alias::exists(&entry.name)? {
+            write_entry(&entry.name, entry)?;
+            imported.push(Imported::Created(entry.name.clone()));
+            continue;
+        }
+
+        match fconflict(&entry.name)? {
+            Conflict::Skip => imported.push(Imported::Skipped(entry.name.clone())),
+            Conflict::Overwrite => {
+                write_entry(&entry.name, entry)?;
+                imported.push(Imported::Overwritten(entry.name.clone()));
+            },
+            Conflict::Rename => {
+                let new_name = free_name(&entry.name)?;
+                write_entry(&new_name, entry)?;
+                imported.push(Imported::Renamed(entry.name.clone(), new_name));
+            },
+        }
+    }
+    Ok(imported)
+}
+
+// entry replaces test and snapshots of overwritten alias
+fn write_entry(name: &str, entry: &Entry) -> io::Result<()> {
+    alias::write(name, &entry.body)?;
+
+    let mut meta = meta::read_sidecar(name)?;
+    meta.description = entry.description.clone();
+    meta.tags = entry.tags.clone();
+    meta::write(name, &meta)?;
+
+    let cfg_list_path = path::cfg_list_path()?;
+    let spec_toml = spec::spec_path(&cfg_list_path, name);
+    match entry.test {
+        Some(ref test) => fs::write(&spec_toml, test)?,
+        None if Path::new(&spec_toml).exists() => fs::remove_file(&spec_toml)?,
+        None => (),
+    }
+
+    let snap_dir = spec::snap_path(&cfg_list_path, name);
+    if Path::new(&snap_dir).is_dir() {
+        fs::remove_dir_all(&snap_dir)?;
+    }
+    if !entry.snapshots.is_empty() {
+        fs::create_dir_all(&snap_dir)?;
+        for (file, value) in &entry.snapshots {
+            fs::write(Path::new(&snap_dir).join(file), value)?;
+        }
+    }
+    Ok(())
+}
+
+// <name>-1, <name>-2, ...
+fn free_name(name: &str) -> io::Result<String> {
+    let mut i = 1;
+    loop {
+        let new_name = format!("{}-{}", name, i);
+        if !alias::exists(&new_name)? {
+            return Ok(new_name);
+        }
+        i += 1;
+    }
+}
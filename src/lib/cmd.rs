@@ -34,6 +34,28 @@ pub fn command_spawn(cmd: &str) -> io::Result<()> {
     Ok(())
 }
 
+// exit status, without failing
+pub fn command_status(cmd: &str) -> io::Result<i32> {
+    let args = split_args(cmd);
+
+    let status = Command::new("cmd")
+        .arg("/c")
+        .args(args)
+        .status()?;
+    Ok(status.code().unwrap_or(1))
+}
+
+// (<stdout>, <stderr>, <status>), without failing
+pub fn command_capture(cmd: &str) -> io::Result<(String, String, i32)> {
+    let args = split_args(cmd);
+
+    let output = Command::new("cmd")
+        .arg("/c")
+        .args(args)
+        .output()?;
+    Ok((encode::to_utf8_string(&output.stdout), encode::to_utf8_string(&output.stderr), output.status.code().unwrap_or(1)))
+}
+
 pub fn split_args(cmd: &str) -> Vec<String> {
     let mut args = Vec::new();
 
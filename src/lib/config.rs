@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::io;
+
+use serde::Deserialize;
+
+use crate::lib::path;
+use crate::lib::diag;
+use crate::lib::meta::Meta;
+
+pub const ALIASES_TOML: &str = "aliases.toml";
+
+// single-file configuration(TOML), many aliases in one file
+//
+//   [[alias]]
+//   name = "hello"
+//   description = "say hello"    # optional, see meta.rs
+//   tags = ["greeting"]
+//   body = """
+//   echo hello $(echo world)
+//   """
+//
+// <name>.txt takes precedence over aliases.toml
+
+#[derive(Deserialize)]
+struct Config {
+    #[serde(default)]
+    alias: Vec<RawEntry>,
+}
+
+#[derive(Deserialize)]
+struct RawEntry {
+    name: String,
+    body: toml::Spanned<String>,
+    description: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    owner: Option<String>,
+    shell: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Entry {
+    pub name: String,
+    pub body: String,
+    // 1-based line of body in aliases.toml
+    pub line: usize,
+    pub meta: Meta,
+}
+
+pub fn toml_path() -> io::Result<String> {
+    Ok(format!("{}\\{}", path::cfg_path()?, ALIASES_TOML))
+}
+
+// empty if aliases.toml doesn't exist, or is broken(warned)
+//
+// parsed once per process, aliases.toml is not written by alias.exe
+pub fn read() -> io::Result<Vec<Entry>> {
+    lazy_static! {
+        static ref ENTRIES: Mutex<Option<Vec<Entry>>> = Mutex::new(None);
+    }
+
+    let mut entries = ENTRIES.lock().unwrap();
+    if entries.is_none() {
+        *entries = Some(parse(&toml_path()?));
+    }
+    Ok(entries.as_ref().unwrap().clone())
+}
+
+// .txt aliases don't depend on aliases.toml, so broken one is not an error
+fn parse(toml_path: &str) -> Vec<Entry> {
+    if !Path::new(toml_path).exists() {
+        return Vec::new();
+    }
+
+    let text = match fs::read_to_string(toml_path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("{}\n", diag::warning(&format!("{}: {}", toml_path, err)).hint("aliases in it are ignored"));
+            return Vec::new();
+        },
+    };
+    let config: Config = match toml::from_str(&text) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}\n", diag::warning(&format!("{}: {}", toml_path, err)).hint("aliases in it are ignored"));
+            return Vec::new();
+        },
+    };
+
+    config.alias.into_iter()
+        .map(|x| {
+            let start = x.body.start();
+            let mut line = text[..start].matches('\n').count() + 1;
+            // multi-line string trims the first newline
+            let quoted = &text[start..];
+            if (quoted.starts_with("\"\"\"") || quoted.starts_with("'''")) && quoted[3..].starts_with(['\n', '\r']) {
+                line += 1;
+            }
+            let meta = Meta { description: x.description, tags: x.tags, owner: x.owner, shell: x.shell, ..Meta::default() };
+            Entry { name: x.name, body: x.body.into_inner(), line, meta }
+        })
+        .collect()
+}
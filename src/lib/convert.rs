@@ -0,0 +1,240 @@
+use std::io;
+
+use regex::Regex;
+
+use crate::lib::diag::{self, Diagnostic};
+
+// alias definitions of other shells, to alias body
+
+pub enum Converted {
+    // (<alias name>, <body>)
+    Alias(String, String),
+    Failed(Box<Diagnostic>),
+}
+
+pub fn convert(format: &str, file: &str, text: &str) -> io::Result<Vec<Converted>> {
+    match format {
+        "bash" | "zsh" => Ok(from_bash(file, text)),
+        "doskey" => Ok(from_doskey(file, text)),
+        "powershell" => Ok(from_powershell(file, text)),
+        _ => Err(diag::error(&format!("unknown format: {}", format))
+            .hint("available formats are bash, zsh, doskey and powershell")
+            .into_error()),
+    }
+}
+
+fn failed(file: &str, line: usize, source: &str, name: &str, reason: &str) -> Converted {
+    Converted::Failed(Box::new(diag::warning(&format!("cannot translate {}: {}", name, reason))
+        .locate(file, line, source)))
+}
+
+// -----
+
+// alias name='value'
+fn from_bash(file: &str, text: &str) -> Vec<Converted> {
+    lazy_static! {
+        static ref RE_ALIAS: Regex = Regex::new(r"^\s*alias\s+(-\w+\s+)?([^=\s]+)=(.*)$").unwrap();
+        static ref RE_FUNCTION: Regex = Regex::new(r"^\s*(function\s+([^\s(]+)|([^\s(]+)\s*\(\))\s*(\{|$)").unwrap();
+        static ref RE_BACKQUOTE: Regex = Regex::new(r"`([^`]*)`").unwrap();
+        static ref RE_ENV: Regex = Regex::new(r"\$\{?([A-Za-z_][A-Za-z0-9_]*)\}?").unwrap();
+        static ref RE_CD: Regex = Regex::new(r"(^|[;&|]\s*)cd(\s|$)").unwrap();
+    }
+
+    let mut converted = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if let Some(caps) = RE_FUNCTION.captures(line) {
+            let name = caps.get(2).or(caps.get(3)).unwrap().as_str();
+            converted.push(failed(file, i + 1, line, name, "shell functions are not supported"));
+            continue;
+        }
+
+        let caps = match RE_ALIAS.captures(line) {
+            Some(caps) => caps,
+            None => continue,
+        };
+        let name = &caps[2];
+        if caps.get(1).is_some() {
+            converted.push(failed(file, i + 1, line, name, &format!("alias option {} is not supported", caps[1].trim())));
+            continue;
+        }
+
+        let value = match unquote_sh(caps[3].trim()) {
+            Some(value) => value.trim_end().to_owned(),
+            None => {
+                converted.push(failed(file, i + 1, line, name, "unbalanced quotes"));
+                continue;
+            },
+        };
+        if RE_CD.is_match(&value) {
+            converted.push(failed(file, i + 1, line, name, "cd can't change the directory of the calling shell"));
+            continue;
+        }
+        if value.contains(';') {
+            converted.push(failed(file, i + 1, line, name, "; separated commands are not supported, use && instead"));
+            continue;
+        }
+
+        let value = RE_BACKQUOTE.replace_all(&value, "$$($1)");
+        let value = RE_ENV.replace_all(&value, "%$1%");
+        converted.push(Converted::Alias(name.to_owned(), format!("{} \"$@\"", value)));
+    }
+    converted
+}
+
+// 'single' "double" or bare
+//
+// bare ~ and ~/ at start of word are expanded, # at start of word is comment
+fn unquote_sh(value: &str) -> Option<String> {
+    let mut s = String::new();
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        let word_start = s.chars().last().map(|x| x.is_whitespace()).unwrap_or(true);
+        match ch {
+            '#' if word_start => break,
+            '~' if word_start && chars.clone().next().map(|x| x == '/' || x.is_whitespace()).unwrap_or(true) => {
+                s.push_str("%USERPROFILE%");
+            },
+            '\'' => {
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        ch => s.push(ch),
+                    }
+                }
+            },
+            '"' => {
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => s.push(chars.next()?),
+                        ch => s.push(ch),
+                    }
+                }
+            },
+            '\\' => s.push(chars.next()?),
+            _ => s.push(ch),
+        }
+    }
+    Some(s)
+}
+
+// -----
+
+// name=command $1 $*
+fn from_doskey(file: &str, text: &str) -> Vec<Converted> {
+    lazy_static! {
+        static ref RE_MACRO: Regex = Regex::new(r"^\s*([^=\s]+)=(.*)$").unwrap();
+        static ref RE_PARAM: Regex = Regex::new(r"\$([1-9*TtGgLlBb$])").unwrap();
+    }
+
+    let mut converted = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        // comment, or [exename] section
+        if trimmed.starts_with(';') || trimmed.starts_with('[') {
+            continue;
+        }
+
+        let caps = match RE_MACRO.captures(line) {
+            Some(caps) => caps,
+            None => continue,
+        };
+        let name = &caps[1];
+
+        let value = RE_PARAM.replace_all(caps[2].trim(), |caps: &regex::Captures| {
+            match &caps[1] {
+                "*" => "\"$@\"".to_owned(),
+                "T" | "t" => "\n".to_owned(),
+                "G" | "g" => ">".to_owned(),
+                "L" | "l" => "<".to_owned(),
+                "B" | "b" => "|".to_owned(),
+                "$" => "$".to_owned(),
+                n => format!("${}", n),
+            }
+        });
+        if value.lines().any(|x| x.trim() == "cd" || x.trim().starts_with("cd ")) {
+            converted.push(failed(file, i + 1, line, name, "cd can't change the directory of the calling shell"));
+            continue;
+        }
+
+        let value: Vec<&str> = value.lines().map(|x| x.trim()).filter(|x| !x.is_empty()).collect();
+        converted.push(Converted::Alias(name.to_owned(), value.join("\n")));
+    }
+    converted
+}
+
+// -----
+
+// Set-Alias name value, or function name { ... }
+fn from_powershell(file: &str, text: &str) -> Vec<Converted> {
+    lazy_static! {
+        static ref RE_ALIAS: Regex = Regex::new(r"(?i)^\s*(?:set|new)-alias\s+(?:-name\s+)?([^\s]+)\s+(?:-value\s+)?([^\s]+)").unwrap();
+        static ref RE_FUNCTION: Regex = Regex::new(r"(?i)^\s*function\s+([^\s{(]+)\s*(\{.*)?$").unwrap();
+        static ref RE_ARGN: Regex = Regex::new(r"\$args\[([0-8])\]").unwrap();
+        static ref RE_VARIABLE: Regex = Regex::new(r"\$[A-Za-z_{]").unwrap();
+        static ref RE_CMDLET: Regex = Regex::new(r"\b[A-Z][a-z]+-[A-Z][A-Za-z]+\b").unwrap();
+    }
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut converted = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+
+        if let Some(caps) = RE_ALIAS.captures(line) {
+            let value = caps[2].trim_matches(|c| c == '\'' || c == '"');
+            if RE_CMDLET.is_match(value) {
+                converted.push(failed(file, i, line, &caps[1], &format!("{} is a PowerShell cmdlet", value)));
+            } else {
+                converted.push(Converted::Alias(caps[1].to_owned(), format!("{} \"$@\"", value)));
+            }
+            continue;
+        }
+
+        let caps = match RE_FUNCTION.captures(line) {
+            Some(caps) => caps,
+            None => continue,
+        };
+        let name = caps[1].to_owned();
+        let start = i;
+
+        // collect function body until braces are balanced
+        let mut body = caps.get(2).map(|x| x.as_str().to_owned()).unwrap_or_default();
+        let mut depth = body.matches('{').count() as i32 - body.matches('}').count() as i32;
+        while (depth > 0 || !body.contains('{')) && i < lines.len() {
+            body.push('\n');
+            body.push_str(lines[i]);
+            depth += lines[i].matches('{').count() as i32 - lines[i].matches('}').count() as i32;
+            i += 1;
+        }
+        let source = lines[start - 1..i].join("\n");
+
+        let inner = match (body.find('{'), body.rfind('}')) {
+            (Some(open), Some(close)) if open < close => &body[open + 1..close],
+            _ => {
+                converted.push(failed(file, start, &source, &name, "unbalanced braces"));
+                continue;
+            },
+        };
+        let inner = RE_ARGN.replace_all(inner, |caps: &regex::Captures| {
+            format!("${}", caps[1].parse::<u32>().unwrap() + 1)
+        });
+        let inner = inner.replace("@args", "\"$@\"").replace("$args", "\"$@\"");
+
+        if inner.to_lowercase().contains("param(") {
+            converted.push(failed(file, start, &source, &name, "param() blocks are not supported"));
+        } else if let Some(m) = RE_CMDLET.find(&inner) {
+            converted.push(failed(file, start, &source, &name, &format!("{} is a PowerShell cmdlet", m.as_str())));
+        } else if RE_VARIABLE.is_match(&inner.replace("\"$@\"", "")) || inner.contains('{') {
+            converted.push(failed(file, start, &source, &name, "PowerShell variables and blocks are not supported"));
+        } else {
+            let value: Vec<&str> = inner.split(['\n', ';'])
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .collect();
+            converted.push(Converted::Alias(name, value.join("\n")));
+        }
+    }
+    converted
+}
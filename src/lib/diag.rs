@@ -0,0 +1,348 @@
+use std::{fmt, ops};
+use std::error;
+use std::io::{self, Error, ErrorKind};
+
+use crate::lib::term;
+
+// rustc-like diagnostic
+//
+//   hello.txt:12:5: error: @popd: directory stack empty
+//      |
+//   12 |     @popd
+//      |     ^^^^^
+//      = hint: @popd needs a preceding @pushd
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    level: Level,
+    message: String,
+    hint: Option<String>,
+    // error location in the (expanded) source text
+    label: Option<Label>,
+    // 0-based line offset in the source, e.g. reported by mruby
+    line: Option<usize>,
+    location: Option<Location>,
+    // e.g. backtrace
+    notes: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Label {
+    text: String,
+    ranges: Vec<ops::Range<usize>>,
+}
+
+#[derive(Debug, Clone)]
+struct Location {
+    file: String,
+    pos: Option<Position>,
+}
+
+#[derive(Debug, Clone)]
+struct Position {
+    line: usize,
+    col: usize,
+    source: String,
+    // char columns in source
+    marks: Vec<ops::Range<usize>>,
+    // expanded text, if it differs from source
+    note: Option<Label>,
+}
+
+pub fn error(message: &str) -> Diagnostic {
+    new(Level::Error, message)
+}
+
+pub fn warning(message: &str) -> Diagnostic {
+    new(Level::Warning, message)
+}
+
+fn new(level: Level, message: &str) -> Diagnostic {
+    Diagnostic {
+        level,
+        message: message.trim().to_owned(),
+        hint: None,
+        label: None,
+        line: None,
+        location: None,
+        notes: Vec::new(),
+    }
+}
+
+impl Diagnostic {
+    pub fn label(mut self, text: &str, range: ops::Range<usize>) -> Diagnostic {
+        self.label = Some(Label { text: text.to_owned(), ranges: vec![range] });
+        self
+    }
+
+    pub fn labels(mut self, text: &str, ranges: Vec<ops::Range<usize>>) -> Diagnostic {
+        self.label = Some(Label { text: text.to_owned(), ranges });
+        self
+    }
+
+    pub fn hint(mut self, hint: &str) -> Diagnostic {
+        self.hint = Some(hint.to_owned());
+        self
+    }
+
+    // e.g. backtrace of script
+    #[cfg(feature = "mruby")]
+    pub fn note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_owned());
+        self
+    }
+
+    // 0-based line in source, of script engine errors
+    #[cfg(any(feature = "mruby", feature = "rhai", feature = "lua"))]
+    pub fn line(mut self, line: usize) -> Diagnostic {
+        self.line = Some(line);
+        self
+    }
+
+    pub fn into_error(self) -> Error {
+        Error::new(ErrorKind::InvalidData, self)
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn hint_text(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.location.as_ref().map(|x| x.file.as_str())
+    }
+
+    // (line, col), 1-based
+    pub fn position(&self) -> Option<(usize, usize)> {
+        self.location.as_ref()
+            .and_then(|x| x.pos.as_ref())
+            .map(|x| (x.line, x.col))
+    }
+
+    // locate to whole file
+    pub fn in_file(mut self, file: &str) -> Diagnostic {
+        self.location = Some(Location { file: file.to_owned(), pos: None });
+        self
+    }
+
+    // locate to file position
+    //
+    // source: original text of alias file, starts at line(1-based)
+    pub fn locate(mut self, file: &str, line: usize, source: &str) -> Diagnostic {
+        if self.location.is_some() {
+            return self;
+        }
+
+        let lines: Vec<&str> = source.split('\n')
+            .map(|x| x.trim_end_matches('\r'))
+            .collect();
+
+        let mut pos = None;
+        if let Some(ref label) = self.label {
+            pos = find_label(&lines, label).map(|(i, source, marks)| Position {
+                line: line + i,
+                col: marks[0].start + 1,
+                source: source.to_owned(),
+                marks,
+                note: None,
+            });
+        }
+        if pos.is_none() {
+            let i = self.line
+                .filter(|&i| i < lines.len())
+                .or_else(|| lines.iter().position(|x| !x.trim().is_empty()))
+                .unwrap_or(0);
+            let source = lines.get(i).unwrap_or(&"");
+            let marks = vec![trimmed_range(source)];
+            pos = Some(Position {
+                line: line + i,
+                col: marks[0].start + 1,
+                source: source.to_string(),
+                marks,
+                note: self.label.take(),
+            });
+        }
+        self.location = Some(Location { file: file.to_owned(), pos });
+
+        self
+    }
+}
+
+// attach label to error, if it doesn't have one yet
+pub fn with_label(err: Error, text: &str, range: ops::Range<usize>) -> Error {
+    let (kind, diag) = into_diagnostic(err);
+    if diag.label.is_some() || diag.location.is_some() {
+        return Error::new(kind, diag);
+    }
+    Error::new(kind, diag.label(text, range))
+}
+
+// attach file location to error
+pub fn locate(err: Error, file: &str, line: usize, source: &str) -> Error {
+    let (kind, diag) = into_diagnostic(err);
+    Error::new(kind, diag.locate(file, line, source))
+}
+
+pub fn from_error(err: Error) -> Diagnostic {
+    into_diagnostic(err).1
+}
+
+fn into_diagnostic(err: Error) -> (ErrorKind, Diagnostic) {
+    let kind = err.kind();
+    if err.get_ref().map(|x| x.is::<Diagnostic>()).unwrap_or(false) {
+        let diag = err.into_inner().unwrap().downcast::<Diagnostic>().unwrap();
+        return (kind, *diag);
+    }
+    (kind, error(&err.to_string()))
+}
+
+fn find_label<'a>(lines: &[&'a str], label: &Label) -> Option<(usize, &'a str, Vec<ops::Range<usize>>)> {
+    // label text is the source line itself, or a part of it
+    for (i, source) in lines.iter().enumerate() {
+        if let Some(offset) = source.find(label.text.as_str()) {
+            let marks = label.ranges.iter()
+                .map(|rng| char_range(source, offset + rng.start..offset + rng.end))
+                .collect();
+            return Some((i, source, marks));
+        }
+    }
+
+    // find first labeled text
+    let first = &label.ranges[0];
+    let text = &label.text[first.clone()];
+    for (i, source) in lines.iter().enumerate() {
+        if let Some(offset) = source.find(text) {
+            return Some((i, source, vec![char_range(source, offset..offset + text.len())]));
+        }
+    }
+
+    None
+}
+
+fn char_range(s: &str, rng: ops::Range<usize>) -> ops::Range<usize> {
+    let start = s[..rng.start].chars().count();
+    let len = s[rng.start..rng.end].chars().count().max(1);
+    start..start + len
+}
+
+fn trimmed_range(s: &str) -> ops::Range<usize> {
+    let start = s.len() - s.trim_start().len();
+    let end = s.trim_end().len().max(start);
+    char_range(s, start..end)
+}
+
+// ---
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ewrite = |s: &str| term::ewrite(s).map_err(|_| fmt::Error);
+        let level = self.level.as_str();
+
+        let (file, pos) = match self.location {
+            Some(Location { ref file, pos: Some(ref pos) }) => (file, pos),
+            Some(Location { ref file, pos: None }) => {
+                write!(f, "{}: {}: {}", file, ewrite(level)?, self.message)?;
+                for note in &self.notes {
+                    write!(f, "\n  = note: {}", note)?;
+                }
+                if let Some(ref hint) = self.hint {
+                    write!(f, "\n  = hint: {}", hint)?;
+                }
+                return Ok(());
+            },
+            None => {
+                write!(f, "{}: {}", ewrite(level)?, self.message)?;
+                if let Some(ref label) = self.label {
+                    write!(f, "\n\n{}", colorize(label).map_err(|_| fmt::Error)?)?;
+                }
+                for note in &self.notes {
+                    write!(f, "\n\nnote: {}", note)?;
+                }
+                if let Some(ref hint) = self.hint {
+                    write!(f, "\n\nhint: {}", hint)?;
+                }
+                return Ok(());
+            },
+        };
+
+        let lineno = pos.line.to_string();
+        let pad = " ".repeat(lineno.len());
+
+        writeln!(f, "{}:{}:{}: {}: {}", file, pos.line, pos.col, ewrite(level)?, self.message)?;
+        writeln!(f, "{} |", pad)?;
+        writeln!(f, "{} | {}", lineno, pos.source)?;
+        write!(f, "{} | {}", pad, ewrite(&underline(&pos.source, &pos.marks))?)?;
+        if let Some(ref note) = pos.note {
+            write!(f, "\n{} = note: expanded to `{}`", pad, colorize(note).map_err(|_| fmt::Error)?)?;
+        }
+        for note in &self.notes {
+            write!(f, "\n{} = note: {}", pad, note)?;
+        }
+        if let Some(ref hint) = self.hint {
+            write!(f, "\n{} = hint: {}", pad, hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for Diagnostic {}
+
+// ^^^ under marks, keeping tabs for alignment
+fn underline(source: &str, marks: &[ops::Range<usize>]) -> String {
+    let end = marks.iter().map(|x| x.end).max().unwrap_or(0);
+    let mut chars = source.chars();
+    let mut s = String::new();
+    for i in 0..end {
+        let ch = chars.next();
+        if marks.iter().any(|x| x.contains(&i)) {
+            s.push('^');
+        } else if ch == Some('\t') {
+            s.push('\t');
+        } else {
+            s.push(' ');
+        }
+    }
+    s
+}
+
+fn colorize(label: &Label) -> io::Result<String> {
+    let mut ranges = label.ranges.clone();
+    ranges.sort_by_key(|x| x.start);
+
+    let mut s = String::new();
+    let mut idx = 0;
+    for rng in ranges {
+        if rng.start < idx {
+            continue;
+        }
+        s.push_str(&label.text[idx..rng.start]);
+        s.push_str(&term::ewrite(&label.text[rng.start..rng.end])?);
+        idx = rng.end;
+    }
+    if idx < label.text.len() {
+        s.push_str(&label.text[idx..]);
+    }
+    Ok(s)
+}
@@ -0,0 +1,122 @@
+use std::{env, io};
+use std::path::PathBuf;
+
+use crate::lib::path;
+use crate::lib::alias::{self, LinkStatus};
+use crate::lib::diag::{self, Diagnostic};
+use crate::lib::exec;
+
+// installation and PATH problems
+pub enum Problem {
+    // config directory can't be resolved, e.g. %USERPROFILE% not set
+    NoConfigDir(String),
+    ListDirNotInPath(PathBuf),
+    // .txt without link
+    MissingLink(String),
+    // dangling, or not pointing at current alias.exe
+    BadLink(String, LinkStatus),
+    // link without .txt
+    Orphan(String),
+    // (<alias name>, <executable found first>)
+    Shadowed(String, PathBuf),
+    Duplicate(Box<Diagnostic>),
+}
+
+impl Problem {
+    pub fn fixable(&self) -> bool {
+        matches!(self, Problem::MissingLink(_) | Problem::BadLink(..) | Problem::Orphan(_))
+    }
+
+    pub fn diag(&self) -> Diagnostic {
+        match self {
+            Problem::NoConfigDir(err) => diag::error(&format!("config directory can't be resolved: {}", err))
+                .hint("set %USERPROFILE%, or %HOMEDRIVE% and %HOMEPATH%"),
+            Problem::ListDirNotInPath(dir) => diag::error(&format!("{} is not on PATH", dir.display()))
+                .hint("add it to PATH, aliases are not found otherwise"),
+            Problem::MissingLink(name) => diag::error(&format!("{}: link is missing", name))
+                .hint("fix with `alias doctor --fix` or `alias repair`"),
+            Problem::BadLink(name, status) => diag::error(&format!("{}: link is {}", name, status.as_str()))
+                .hint("fix with `alias doctor --fix`"),
+            Problem::Orphan(name) => diag::warning(&format!("{}: link has no .txt", name))
+                .hint("remove with `alias doctor --fix`, or define it with `alias edit`"),
+            Problem::Shadowed(name, other) => diag::warning(&format!("{}: alias is shadowed by {}", name, other.display()))
+                .hint("move the list directory earlier in PATH, or rename the alias"),
+            Problem::Duplicate(diag) => Diagnostic::clone(diag),
+        }
+    }
+}
+
+pub fn check() -> io::Result<Vec<Problem>> {
+    let mut problems = Vec::new();
+
+    let cfg_list_path = match path::cfg_list_path() {
+        Ok(cfg_list_path) => cfg_list_path,
+        Err(err) => {
+            // nothing else can be checked
+            problems.push(Problem::NoConfigDir(err.to_string()));
+            return Ok(problems);
+        },
+    };
+
+    let list_dir = path::alias_list_dir();
+    let in_path = env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| path::same_path(&dir, &list_dir)))
+        .unwrap_or(false);
+    if !in_path {
+        problems.push(Problem::ListDirNotInPath(list_dir.clone()));
+    }
+
+    for (key, _cfg_path) in alias::cfg_iter()? {
+        match alias::link_status(&key) {
+            LinkStatus::Ok => (),
+            LinkStatus::Missing => problems.push(Problem::MissingLink(key.clone())),
+            status => problems.push(Problem::BadLink(key.clone(), status)),
+        }
+
+        if in_path {
+            let found = path::which(&key);
+            let i = found.iter().position(|x| x.parent().map(|dir| path::same_path(dir, &list_dir)).unwrap_or(false));
+            if i.map(|i| i > 0).unwrap_or(false) {
+                problems.push(Problem::Shadowed(key.clone(), found[0].clone()));
+            }
+        }
+    }
+
+    for (key, _value) in alias::list_iter()? {
+        if exec::read(&cfg_list_path, &key).is_err() {
+            problems.push(Problem::Orphan(key));
+        }
+    }
+
+    for diag in alias::duplicates()? {
+        problems.push(Problem::Duplicate(Box::new(diag)));
+    }
+
+    Ok(problems)
+}
+
+// fconfirm: asks before removing
+pub fn fix(problem: &Problem, fconfirm: impl Fn(&str) -> io::Result<bool>) -> io::Result<bool> {
+    match problem {
+        Problem::MissingLink(name) => {
+            alias::mklink(name)?;
+            Ok(true)
+        },
+        Problem::BadLink(name, status) => {
+            // may be a real executable, not a link
+            if *status == LinkStatus::Wrong && !fconfirm(&format!("replace {}.exe with a link to alias.exe?", name))? {
+                return Ok(false);
+            }
+            alias::repair(name, false, true)?;
+            Ok(true)
+        },
+        Problem::Orphan(name) => {
+            if !fconfirm(&format!("remove {}.exe?", name))? {
+                return Ok(false);
+            }
+            alias::remove_link(name)?;
+            Ok(true)
+        },
+        _ => Ok(false),
+    }
+}
@@ -0,0 +1,620 @@
+use std::{env, io, ops};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::lib::diag;
+use crate::lib::exec;
+use crate::lib::dsl::{self, Context, ScriptEngine, ScriptFn, ScriptValue};
+
+// built-in expression language for <%= %>, without mruby
+//
+//   <%= "hello".upcase + " " + ARGV[0] %>
+//   <%= ARGV.length > 1 ? ARGV[1] : "default" %>
+//   <%= ENV["USERNAME"].downcase %>
+//   <%= (1 + 2) * 3 %>
+//
+// literals: "string" 'string' 1 1.5 true false nil
+// operators: + - * / % == != < <= > >= && || ! ?:
+// names: ARGV, ENV, ALIAS_NAME
+// functions: Alias runtime(see dsl::def_runtime), e.g. capture("git rev-parse HEAD").strip
+
+// ScriptEngine of <%= %>, without mruby
+pub struct ExprEngine {
+    args: Vec<String>,
+    alias_name: String,
+    fns: HashMap<String, ScriptFn>,
+    state: Rc<exec::RunState>,
+}
+
+impl ExprEngine {
+    pub fn new(ctx: &Context) -> io::Result<ExprEngine> {
+        let mut engine = ExprEngine {
+            args: ctx.args.clone(),
+            alias_name: ctx.alias_name().to_owned(),
+            fns: HashMap::new(),
+            state: ctx.state.clone(),
+        };
+        dsl::def_runtime(&mut engine)?;
+        Ok(engine)
+    }
+
+    fn run(&self, source: &str) -> io::Result<Value> {
+        let expr = parse(source)?;
+        Evaluator { source, engine: self }.eval(&expr)
+    }
+}
+
+impl ScriptEngine for ExprEngine {
+    fn eval(&mut self, source: &str) -> io::Result<()> {
+        self.run(source)?;
+        Ok(())
+    }
+
+    fn eval_to_string(&mut self, source: &str) -> io::Result<String> {
+        Ok(value2str(&self.run(source)?))
+    }
+
+    fn check(&mut self, source: &str) -> io::Result<()> {
+        parse(source)?;
+        Ok(())
+    }
+
+    fn def_fn(&mut self, name: &str, f: ScriptFn) -> io::Result<()> {
+        self.fns.insert(name.to_owned(), f);
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Value {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Str(_) => "string",
+            Value::Array(_) => "array",
+        }
+    }
+
+    fn to_s(&self) -> String {
+        match self {
+            Value::Nil => String::new(),
+            Value::Bool(b) => b.to_string(),
+            Value::Int(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Str(s) => s.clone(),
+            Value::Array(a) => a.iter().map(|x| x.to_s()).collect::<Vec<String>>().join(" "),
+        }
+    }
+}
+
+// same as dsl::mruby::value2str, string is quoted
+fn value2str(value: &Value) -> String {
+    match value {
+        Value::Nil => String::new(),
+        Value::Str(s) => format!(r#""{}""#, s),
+        Value::Array(a) => format!("[{}]", a.iter().map(value2str).collect::<Vec<String>>().join(", ")),
+        _ => value.to_s(),
+    }
+}
+
+fn parse(source: &str) -> io::Result<Expr> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { source, tokens, pos: 0 };
+    let expr = parser.expr()?;
+    if let Some(token) = parser.peek() {
+        return Err(error(source, "unexpected token", token.range.clone()));
+    }
+    Ok(expr)
+}
+
+fn error(source: &str, message: &str, range: ops::Range<usize>) -> io::Error {
+    diag::error(&format!("expr: {}", message))
+        .label(source, range)
+        .into_error()
+}
+
+// ---
+
+#[derive(Clone, PartialEq, Debug)]
+enum Tok {
+    Num(Value),
+    Str(String),
+    Ident(String),
+    // operator or punctuation
+    Op(&'static str),
+}
+
+#[derive(Debug)]
+struct Token {
+    tok: Tok,
+    range: ops::Range<usize>,
+}
+
+fn tokenize(source: &str) -> io::Result<Vec<Token>> {
+    const OPS: &[&str] = &[
+        "==", "!=", "<=", ">=", "&&", "||",
+        "+", "-", "*", "/", "%", "<", ">", "!", "?", ":", "(", ")", "[", "]", ".", ",",
+    ];
+
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    'outer: while i < bytes.len() {
+        let c = bytes[i] as char;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        if c.is_ascii_digit() {
+            while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'_') {
+                i += 1;
+            }
+            let mut float = false;
+            if i + 1 < bytes.len() && bytes[i] == b'.' && bytes[i + 1].is_ascii_digit() {
+                float = true;
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let text = source[start..i].replace('_', "");
+            let value = if float {
+                text.parse().map(Value::Float).ok()
+            } else {
+                text.parse().map(Value::Int).ok()
+            };
+            match value {
+                Some(value) => tokens.push(Token { tok: Tok::Num(value), range: start..i }),
+                None => return Err(error(source, "invalid number", start..i)),
+            }
+            continue;
+        }
+
+        if c == '"' || c == '\'' {
+            let mut s = String::new();
+            let mut chars = source[i + 1..].char_indices();
+            while let Some((j, ch)) = chars.next() {
+                match ch {
+                    _ if ch == c => {
+                        i += 1 + j + 1;
+                        tokens.push(Token { tok: Tok::Str(s), range: start..i });
+                        continue 'outer;
+                    },
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) if c == '"' => s.push('\n'),
+                        Some((_, 't')) if c == '"' => s.push('\t'),
+                        Some((_, ch)) => s.push(ch),
+                        None => break,
+                    },
+                    _ => s.push(ch),
+                }
+            }
+            return Err(error(source, "unterminated string", start..source.len()));
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            // predicate method, e.g. include?
+            if i < bytes.len() && bytes[i] == b'?' && tokens.last().map(|x: &Token| x.tok == Tok::Op(".")).unwrap_or(false) {
+                i += 1;
+            }
+            tokens.push(Token { tok: Tok::Ident(source[start..i].to_owned()), range: start..i });
+            continue;
+        }
+
+        for op in OPS {
+            if source[i..].starts_with(op) {
+                i += op.len();
+                tokens.push(Token { tok: Tok::Op(op), range: start..i });
+                continue 'outer;
+            }
+        }
+        let end = start + source[start..].chars().next().unwrap().len_utf8();
+        return Err(error(source, &format!("unexpected character `{}`", &source[start..end]), start..end));
+    }
+    Ok(tokens)
+}
+
+// ---
+
+#[derive(Debug)]
+enum Expr {
+    Lit(Value),
+    Name(String, ops::Range<usize>),
+    Unary(&'static str, Box<Expr>, ops::Range<usize>),
+    Binary(&'static str, Box<Expr>, Box<Expr>, ops::Range<usize>),
+    Cond(Box<Expr>, Box<Expr>, Box<Expr>),
+    Index(Box<Expr>, Box<Expr>, ops::Range<usize>),
+    // (<receiver>, <method>, <args>)
+    Call(Box<Expr>, String, Vec<Expr>, ops::Range<usize>),
+    // (<function>, <args>)
+    Func(String, Vec<Expr>, ops::Range<usize>),
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, op: &str) -> Option<ops::Range<usize>> {
+        match self.peek() {
+            Some(Token { tok: Tok::Op(x), range }) if *x == op => {
+                let range = range.clone();
+                self.pos += 1;
+                Some(range)
+            },
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> io::Result<()> {
+        if self.eat(op).is_some() {
+            return Ok(());
+        }
+        let range = self.peek().map(|x| x.range.clone()).unwrap_or(self.source.len()..self.source.len());
+        Err(error(self.source, &format!("expected `{}`", op), range))
+    }
+
+    fn expr(&mut self) -> io::Result<Expr> {
+        let cond = self.binary(0)?;
+        if self.eat("?").is_some() {
+            let then = self.expr()?;
+            self.expect(":")?;
+            let els = self.expr()?;
+            return Ok(Expr::Cond(Box::new(cond), Box::new(then), Box::new(els)));
+        }
+        Ok(cond)
+    }
+
+    // precedence climbing, lowest first
+    fn binary(&mut self, level: usize) -> io::Result<Expr> {
+        const LEVELS: &[&[&str]] = &[
+            &["||"],
+            &["&&"],
+            &["==", "!=", "<", "<=", ">", ">="],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        loop {
+            let op = match self.peek() {
+                Some(Token { tok: Tok::Op(op), .. }) if LEVELS[level].contains(op) => *op,
+                _ => break,
+            };
+            let range = self.eat(op).unwrap();
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs), range);
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> io::Result<Expr> {
+        for op in &["!", "-"] {
+            if let Some(range) = self.eat(op) {
+                return Ok(Expr::Unary(op, Box::new(self.unary()?), range));
+            }
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> io::Result<Expr> {
+        let mut expr = self.primary()?;
+        loop {
+            if let Some(start) = self.eat("[") {
+                let index = self.expr()?;
+                self.expect("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index), start);
+            } else if self.eat(".").is_some() {
+                let (name, range) = match self.peek() {
+                    Some(Token { tok: Tok::Ident(name), range }) => (name.clone(), range.clone()),
+                    _ => {
+                        let range = self.peek().map(|x| x.range.clone()).unwrap_or(self.source.len()..self.source.len());
+                        return Err(error(self.source, "expected method name", range));
+                    },
+                };
+                self.pos += 1;
+                let args = if self.eat("(").is_some() { self.args()? } else { Vec::new() };
+                expr = Expr::Call(Box::new(expr), name, args, range);
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn args(&mut self) -> io::Result<Vec<Expr>> {
+        let mut args = Vec::new();
+        if self.eat(")").is_some() {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr()?);
+            if self.eat(")").is_some() {
+                return Ok(args);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn primary(&mut self) -> io::Result<Expr> {
+        let token = match self.tokens.get(self.pos) {
+            Some(token) => token,
+            None => return Err(error(self.source, "unexpected end of expression", self.source.len()..self.source.len())),
+        };
+        let (tok, range) = (token.tok.clone(), token.range.clone());
+        self.pos += 1;
+
+        match tok {
+            Tok::Num(value) => Ok(Expr::Lit(value)),
+            Tok::Str(s) => Ok(Expr::Lit(Value::Str(s))),
+            Tok::Ident(name) => match name.as_str() {
+                "true" => Ok(Expr::Lit(Value::Bool(true))),
+                "false" => Ok(Expr::Lit(Value::Bool(false))),
+                "nil" => Ok(Expr::Lit(Value::Nil)),
+                _ if self.eat("(").is_some() => Ok(Expr::Func(name, self.args()?, range)),
+                _ => Ok(Expr::Name(name, range)),
+            },
+            Tok::Op("(") => {
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            },
+            Tok::Op(_) => Err(error(self.source, "unexpected token", range)),
+        }
+    }
+}
+
+// ---
+
+struct Evaluator<'a> {
+    source: &'a str,
+    engine: &'a ExprEngine,
+}
+
+impl<'a> Evaluator<'a> {
+    fn eval(&self, expr: &Expr) -> io::Result<Value> {
+        match expr {
+            Expr::Lit(value) => Ok(value.clone()),
+            Expr::Name(name, range) => match name.as_str() {
+                "ARGV" => Ok(Value::Array(self.engine.args.iter().skip(1).map(|x| Value::Str(x.clone())).collect())),
+                "ALIAS_NAME" => Ok(Value::Str(self.engine.alias_name.clone())),
+                // indexed only, ENV["NAME"]
+                "ENV" => Ok(Value::Nil),
+                _ => Err(error(self.source, &format!("undefined name `{}`", name), range.clone())),
+            },
+            Expr::Unary(op, rhs, range) => {
+                let rhs = self.eval(rhs)?;
+                match (*op, rhs) {
+                    ("!", rhs) => Ok(Value::Bool(!rhs.truthy())),
+                    ("-", Value::Int(i)) => i.checked_neg().map(Value::Int).ok_or_else(|| error(self.source, "integer overflow", range.clone())),
+                    ("-", Value::Float(f)) => Ok(Value::Float(-f)),
+                    (_, rhs) => Err(error(self.source, &format!("can't negate {}", rhs.type_name()), range.clone())),
+                }
+            },
+            Expr::Binary(op, lhs, rhs, range) => {
+                // short circuit
+                match *op {
+                    "&&" => {
+                        let lhs = self.eval(lhs)?;
+                        return if lhs.truthy() { self.eval(rhs) } else { Ok(lhs) };
+                    },
+                    "||" => {
+                        let lhs = self.eval(lhs)?;
+                        return if lhs.truthy() { Ok(lhs) } else { self.eval(rhs) };
+                    },
+                    _ => (),
+                }
+                let (lhs, rhs) = (self.eval(lhs)?, self.eval(rhs)?);
+                self.binary(op, lhs, rhs, range)
+            },
+            Expr::Cond(cond, then, els) => {
+                if self.eval(cond)?.truthy() { self.eval(then) } else { self.eval(els) }
+            },
+            Expr::Index(target, index, range) => {
+                if let Expr::Name(name, _) = &**target {
+                    if name == "ENV" {
+                        let key = self.eval(index)?.to_s();
+                        return Ok(env::var(key).map(Value::Str).unwrap_or(Value::Nil));
+                    }
+                }
+                match (self.eval(target)?, self.eval(index)?) {
+                    (Value::Array(a), Value::Int(i)) => Ok(nth(&a, i).cloned().unwrap_or(Value::Nil)),
+                    (Value::Str(s), Value::Int(i)) => {
+                        let chars: Vec<Value> = s.chars().map(|x| Value::Str(x.to_string())).collect();
+                        Ok(nth(&chars, i).cloned().unwrap_or(Value::Nil))
+                    },
+                    (target, index) => Err(error(self.source, &format!("can't index {} with {}", target.type_name(), index.type_name()), range.clone())),
+                }
+            },
+            Expr::Call(receiver, name, args, range) => {
+                let receiver = self.eval(receiver)?;
+                let args = args.iter().map(|x| self.eval(x)).collect::<io::Result<Vec<Value>>>()?;
+                self.call(receiver, name, args, range)
+            },
+            Expr::Func(name, args, range) => {
+                let f = match self.engine.fns.get(name) {
+                    Some(f) => f,
+                    None => return Err(error(self.source, &format!("undefined function `{}`", name), range.clone())),
+                };
+                let args = args.iter().map(|x| self.eval(x).map(|x| x.to_s())).collect::<io::Result<Vec<String>>>()?;
+                match f(&self.engine.state, &args)? {
+                    ScriptValue::Nil => Ok(Value::Nil),
+                    ScriptValue::Int(i) => Ok(Value::Int(i)),
+                    ScriptValue::Str(s) => Ok(Value::Str(s)),
+                }
+            },
+        }
+    }
+
+    fn binary(&self, op: &str, lhs: Value, rhs: Value, range: &ops::Range<usize>) -> io::Result<Value> {
+        use Value::*;
+
+        let err = |lhs: &Value, rhs: &Value| {
+            Err(error(self.source, &format!("can't apply `{}` to {} and {}", op, lhs.type_name(), rhs.type_name()), range.clone()))
+        };
+        let overflow = || error(self.source, "integer overflow", range.clone());
+        // far beyond a command line
+        const MAX_STR_LEN: usize = 1 << 20;
+        match op {
+            "==" => return Ok(Bool(equal(&lhs, &rhs))),
+            "!=" => return Ok(Bool(!equal(&lhs, &rhs))),
+            _ => (),
+        }
+
+        match (&lhs, &rhs) {
+            (Int(a), Int(b)) => match op {
+                "+" => a.checked_add(*b).map(Int).ok_or_else(overflow),
+                "-" => a.checked_sub(*b).map(Int).ok_or_else(overflow),
+                "*" => a.checked_mul(*b).map(Int).ok_or_else(overflow),
+                "/" | "%" if *b == 0 => Err(error(self.source, "divided by 0", range.clone())),
+                "/" => floor_div(*a, *b).map(Int).ok_or_else(overflow),
+                "%" => Ok(Int(floor_mod(*a, *b))),
+                _ => Ok(Bool(compare(op, Some(a.cmp(b))))),
+            },
+            (Int(_), Float(_)) | (Float(_), Int(_)) | (Float(_), Float(_)) => {
+                let (a, b) = (to_f64(&lhs), to_f64(&rhs));
+                match op {
+                    "+" => Ok(Float(a + b)),
+                    "-" => Ok(Float(a - b)),
+                    "*" => Ok(Float(a * b)),
+                    "/" => Ok(Float(a / b)),
+                    "%" => Ok(Float(a - b * (a / b).floor())),
+                    _ => Ok(Bool(compare(op, a.partial_cmp(&b)))),
+                }
+            },
+            (Str(a), Str(b)) => match op {
+                "+" => Ok(Str(format!("{}{}", a, b))),
+                "<" | "<=" | ">" | ">=" => Ok(Bool(compare(op, a.partial_cmp(b)))),
+                _ => err(&lhs, &rhs),
+            },
+            (Str(a), Int(n)) if op == "*" && *n >= 0 => match a.len().checked_mul(*n as usize) {
+                Some(len) if len <= MAX_STR_LEN => Ok(Str(a.repeat(*n as usize))),
+                _ => Err(error(self.source, "string too long", range.clone())),
+            },
+            _ => err(&lhs, &rhs),
+        }
+    }
+
+    fn call(&self, receiver: Value, name: &str, args: Vec<Value>, range: &ops::Range<usize>) -> io::Result<Value> {
+        use Value::*;
+
+        let arg = |i: usize| args.get(i).map(|x| x.to_s()).unwrap_or_default();
+        let value = match (&receiver, name, args.len()) {
+            (_, "to_s", 0) => Str(receiver.to_s()),
+            (_, "nil?", 0) => Bool(receiver == Nil),
+            (Nil, "empty?", 0) => Bool(true),
+            (_, "to_i", 0) => Int(match &receiver {
+                Int(i) => *i,
+                Float(f) => *f as i64,
+                _ => receiver.to_s().trim().parse().unwrap_or(0),
+            }),
+            (_, "to_f", 0) => Float(match &receiver {
+                Int(i) => *i as f64,
+                Float(f) => *f,
+                _ => receiver.to_s().trim().parse().unwrap_or(0.0),
+            }),
+
+            (Str(s), "upcase", 0) => Str(s.to_uppercase()),
+            (Str(s), "downcase", 0) => Str(s.to_lowercase()),
+            (Str(s), "capitalize", 0) => Str(s.chars().take(1).flat_map(|x| x.to_uppercase()).chain(s.chars().skip(1).flat_map(|x| x.to_lowercase())).collect()),
+            (Str(s), "strip", 0) => Str(s.trim().to_owned()),
+            (Str(s), "reverse", 0) => Str(s.chars().rev().collect()),
+            (Str(s), "length", 0) | (Str(s), "size", 0) => Int(s.chars().count() as i64),
+            (Str(s), "empty?", 0) => Bool(s.is_empty()),
+            (Str(s), "include?", 1) => Bool(s.contains(&arg(0))),
+            (Str(s), "start_with?", 1) => Bool(s.starts_with(&arg(0))),
+            (Str(s), "end_with?", 1) => Bool(s.ends_with(&arg(0))),
+            (Str(s), "sub", 2) => Str(s.replacen(&arg(0), &arg(1), 1)),
+            (Str(s), "gsub", 2) => Str(s.replace(&arg(0), &arg(1))),
+            (Str(s), "split", 1) => Array(s.split(&arg(0)).map(|x| Str(x.to_owned())).collect()),
+            (Str(s), "split", 0) => Array(s.split_whitespace().map(|x| Str(x.to_owned())).collect()),
+
+            (Array(a), "length", 0) | (Array(a), "size", 0) => Int(a.len() as i64),
+            (Array(a), "empty?", 0) => Bool(a.is_empty()),
+            (Array(a), "first", 0) => a.first().cloned().unwrap_or(Nil),
+            (Array(a), "last", 0) => a.last().cloned().unwrap_or(Nil),
+            (Array(a), "join", 0) => Str(a.iter().map(|x| x.to_s()).collect()),
+            (Array(a), "join", 1) => Str(a.iter().map(|x| x.to_s()).collect::<Vec<String>>().join(&arg(0))),
+            (Array(a), "include?", 1) => Bool(a.iter().any(|x| equal(x, &args[0]))),
+
+            (Int(i), "abs", 0) => Int(i.checked_abs().ok_or_else(|| error(self.source, "integer overflow", range.clone()))?),
+            (Float(f), "abs", 0) => Float(f.abs()),
+            (Float(f), "round", 0) => Int(f.round() as i64),
+
+            _ => return Err(error(self.source, &format!("undefined method `{}` for {} with {} argument(s)", name, receiver.type_name(), args.len()), range.clone())),
+        };
+        Ok(value)
+    }
+}
+
+fn nth(a: &[Value], i: i64) -> Option<&Value> {
+    let i = if i < 0 { a.len() as i64 + i } else { i };
+    if i < 0 { None } else { a.get(i as usize) }
+}
+
+// rounds toward negative infinity, same as ruby
+// None on overflow, i64::MIN / -1
+fn floor_div(a: i64, b: i64) -> Option<i64> {
+    let q = a.checked_div(b)?;
+    Some(if a % b != 0 && (a < 0) != (b < 0) { q - 1 } else { q })
+}
+
+// sign of divisor, as Ruby
+fn floor_mod(a: i64, b: i64) -> i64 {
+    let r = a.wrapping_rem(b);
+    if r != 0 && (r < 0) != (b < 0) { r + b } else { r }
+}
+
+fn to_f64(value: &Value) -> f64 {
+    match value {
+        Value::Int(i) => *i as f64,
+        Value::Float(f) => *f,
+        _ => 0.0,
+    }
+}
+
+fn equal(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => to_f64(lhs) == to_f64(rhs),
+        _ => lhs == rhs,
+    }
+}
+
+fn compare(op: &str, ord: Option<std::cmp::Ordering>) -> bool {
+    use std::cmp::Ordering::*;
+
+    matches!((op, ord),
+        ("<", Some(Less)) |
+        ("<=", Some(Less)) | ("<=", Some(Equal)) |
+        (">", Some(Greater)) |
+        (">=", Some(Greater)) | (">=", Some(Equal)))
+}
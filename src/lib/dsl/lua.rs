@@ -0,0 +1,111 @@
+use std::io;
+use std::rc::Rc;
+
+use mlua::{Lua, Value, Variadic};
+use regex::Regex;
+
+use crate::lib::diag;
+use crate::lib::exec;
+use crate::lib::dsl::{self, Context, ScriptEngine, ScriptFn, ScriptValue};
+
+// ScriptEngine of ```lua blocks
+//
+//   local name = ARGV[1] or "world"
+//   run("echo hello " .. name)
+//
+// ARGV, ALIAS_NAME and Alias runtime(see dsl::def_runtime) are defined,
+// globals are kept across blocks
+pub struct LuaEngine {
+    lua: Lua,
+    // chunk name, for error messages
+    file: String,
+    state: Rc<exec::RunState>,
+}
+
+impl LuaEngine {
+    pub fn new(ctx: &Context) -> io::Result<LuaEngine> {
+        let lua = Lua::new();
+        {
+            let globals = lua.globals();
+            globals.set("ARGV", ctx.args.iter().skip(1).cloned().collect::<Vec<String>>()).map_err(to_io_error)?;
+            globals.set("ALIAS_NAME", ctx.alias_name()).map_err(to_io_error)?;
+        }
+
+        // '=' is used as is, not [string "..."]
+        let mut engine = LuaEngine { lua, file: format!("={}", ctx.file), state: ctx.state.clone() };
+        dsl::def_runtime(&mut engine)?;
+        Ok(engine)
+    }
+}
+
+impl ScriptEngine for LuaEngine {
+    fn eval(&mut self, source: &str) -> io::Result<()> {
+        self.lua.load(source).set_name(&self.file).exec().map_err(to_io_error)
+    }
+
+    fn eval_to_string(&mut self, source: &str) -> io::Result<String> {
+        let value: Value = self.lua.load(source).set_name(&self.file).eval().map_err(to_io_error)?;
+        value2str(value)
+    }
+
+    fn check(&mut self, source: &str) -> io::Result<()> {
+        self.lua.load(source).set_name(&self.file).into_function().map(|_| ()).map_err(to_io_error)
+    }
+
+    fn def_fn(&mut self, name: &str, f: ScriptFn) -> io::Result<()> {
+        let state = self.state.clone();
+        let func = self.lua.create_function(move |lua, args: Variadic<String>| {
+            match f(&state, &args) {
+                Ok(ScriptValue::Nil) => Ok(Value::Nil),
+                Ok(ScriptValue::Int(i)) => Ok(Value::Integer(i)),
+                Ok(ScriptValue::Str(s)) => lua.create_string(&s).map(Value::String),
+                // without color
+                Err(err) => Err(mlua::Error::RuntimeError(diag::from_error(err).message().to_owned())),
+            }
+        }).map_err(to_io_error)?;
+        self.lua.globals().set(name, func).map_err(to_io_error)
+    }
+}
+
+fn to_io_error(err: mlua::Error) -> io::Error {
+    lazy_static! {
+        // <file>:<line>: <message>
+        static ref RE_LUA_ERROR: Regex = Regex::new(r"^.*?:(\d+):\s*(.*)$").unwrap();
+    }
+
+    let message = match err {
+        mlua::Error::SyntaxError { message, .. } => message,
+        mlua::Error::RuntimeError(message) => message,
+        mlua::Error::CallbackError { cause, .. } => return to_io_error((*cause).clone()),
+        err => err.to_string(),
+    };
+    // without traceback
+    let message = message.lines().next().unwrap_or("").to_owned();
+
+    if let Some(caps) = RE_LUA_ERROR.captures(&message) {
+        let line: usize = caps[1].parse().unwrap();
+        return diag::error(&format!("lua: {}", &caps[2]))
+            .line(line.saturating_sub(1))
+            .into_error();
+    }
+    diag::error(&format!("lua: {}", message)).into_error()
+}
+
+// same as dsl::mruby::value2str, string is quoted
+fn value2str(value: Value) -> io::Result<String> {
+    match value {
+        Value::Nil => Ok(String::new()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Number(f) => Ok(f.to_string()),
+        Value::String(s) => Ok(format!(r#""{}""#, s.to_str().map_err(to_io_error)?)),
+        // sequence
+        Value::Table(t) => {
+            let a = t.sequence_values::<Value>()
+                .map(|x| x.map_err(to_io_error).and_then(value2str))
+                .collect::<io::Result<Vec<String>>>()?;
+            Ok(format!("[{}]", a.join(", ")))
+        },
+        value => Err(diag::error(&format!("lua: {} can't be converted to string", value.type_name())).into_error()),
+    }
+}
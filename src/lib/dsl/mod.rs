@@ -1 +1,139 @@
+use std::{env, io};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::lib::cmd;
+use crate::lib::diag;
+use crate::lib::exec;
+
+#[cfg(feature = "mruby")]
 pub mod mruby;
+#[cfg(feature = "mruby")]
+pub mod mrbc;
+#[cfg(feature = "rhai")]
+pub mod rhai;
+#[cfg(feature = "lua")]
+pub mod lua;
+#[cfg(not(feature = "mruby"))]
+pub mod expr;
+
+// scripting language of fenced blocks, e.g. ```ruby
+//
+// an engine is created on first block of its language, and lives until alias ends
+pub trait ScriptEngine {
+    // run block
+    fn eval(&mut self, source: &str) -> io::Result<()>;
+    // value of <%= %>, as cmd string
+    fn eval_to_string(&mut self, source: &str) -> io::Result<String>;
+    // compile only, don't run
+    fn check(&mut self, source: &str) -> io::Result<()>;
+    // global function, callable from script
+    fn def_fn(&mut self, name: &str, f: ScriptFn) -> io::Result<()>;
+    // @<name> registered by this engine, see exec::register_directive
+    fn call_directive(&mut self, name: &str, _args: &[String]) -> io::Result<()> {
+        Err(diag::error(&format!("@{} is not registered by this engine", name)).into_error())
+    }
+}
+
+// arguments are converted to string, state is of the run calling it
+pub type ScriptFn = fn(&exec::RunState, &[String]) -> io::Result<ScriptValue>;
+
+pub enum ScriptValue {
+    Nil,
+    Int(i64),
+    Str(String),
+}
+
+// alias given to engine
+pub struct Context<'a> {
+    // <name>.txt or aliases.toml, for error messages of mruby and lua
+    #[cfg_attr(not(any(feature = "mruby", feature = "lua")), allow(dead_code))]
+    pub file: &'a str,
+    // arguments of alias, args[0] is alias exe
+    pub args: &'a Vec<String>,
+    // directory stack and exit status, for runtime functions
+    pub state: Rc<exec::RunState>,
+}
+
+impl<'a> Context<'a> {
+    pub fn alias_name(&self) -> &str {
+        Path::new(&self.args[0]).file_stem()
+            .and_then(|x| x.to_str())
+            .unwrap_or("")
+    }
+}
+
+// (<language of fence>, <cargo feature>)
+pub const LANGS: &[(&str, &str)] = &[
+    ("ruby", "mruby"),
+    ("rhai", "rhai"),
+    ("lua", "lua"),
+];
+
+// language of <%= %>
+#[cfg(feature = "mruby")]
+pub const NESTED_LANG: &str = "ruby";
+#[cfg(not(feature = "mruby"))]
+pub const NESTED_LANG: &str = "expr";
+
+pub fn new_engine(lang: &str, ctx: &Context) -> io::Result<Box<dyn ScriptEngine>> {
+    match lang {
+        #[cfg(feature = "mruby")]
+        "ruby" => Ok(Box::new(mruby::MrubyEngine::new(ctx)?)),
+        #[cfg(feature = "rhai")]
+        "rhai" => Ok(Box::new(rhai::RhaiEngine::new(ctx)?)),
+        #[cfg(feature = "lua")]
+        "lua" => Ok(Box::new(lua::LuaEngine::new(ctx)?)),
+        #[cfg(not(feature = "mruby"))]
+        "expr" => Ok(Box::new(expr::ExprEngine::new(ctx)?)),
+        _ => Err(not_built_in(lang).into_error()),
+    }
+}
+
+// engine only for ScriptEngine::check, user code is not run by it,
+// e.g. user library of mruby
+pub fn new_checker(lang: &str, ctx: &Context) -> io::Result<Box<dyn ScriptEngine>> {
+    match lang {
+        #[cfg(feature = "mruby")]
+        "ruby" => Ok(Box::new(mruby::MrubyEngine::checker(ctx))),
+        _ => new_engine(lang, ctx),
+    }
+}
+
+// alias.exe was built without engine of lang
+pub fn not_built_in(lang: &str) -> diag::Diagnostic {
+    let feature = LANGS.iter().find(|x| x.0 == lang).map(|x| x.1).unwrap_or(lang);
+    diag::error(&format!("{} block needs {}, which is not built in", lang, feature))
+        .hint(&format!("rebuild alias.exe with `cargo build --features {}`", feature))
+}
+
+// ---
+
+// Alias runtime as global functions, for every engine
+//
+//   run("git status")            # => exit status
+//   capture("git rev-parse HEAD") # => stdout
+//   setenv("KEY", "value")       # same as @set
+//   pushd("dir"), popd()         # same as @pushd, @popd
+//   env("KEY")                   # => value or nil
+//   exit(status)                 # terminate alias
+pub fn def_runtime(engine: &mut dyn ScriptEngine) -> io::Result<()> {
+    engine.def_fn("run", |_, args| Ok(ScriptValue::Int(cmd::command_status(arg(args, 0))? as i64)))?;
+    engine.def_fn("capture", |_, args| Ok(ScriptValue::Str(cmd::command_capture(arg(args, 0))?.0)))?;
+    engine.def_fn("setenv", |_, args| exec::setenv(arg(args, 0), arg(args, 1)).map(|_| ScriptValue::Nil))?;
+    engine.def_fn("pushd", |state, args| state.pushd(arg(args, 0)).map(|_| ScriptValue::Nil))?;
+    engine.def_fn("popd", |state, _| state.popd().map(|_| ScriptValue::Nil))?;
+    engine.def_fn("env", |_, args| Ok(env::var(arg(args, 0)).map(ScriptValue::Str).unwrap_or(ScriptValue::Nil)))?;
+    engine.def_fn("exit", |state, args| match arg(args, 0) {
+        "" => Err(state.exit(0)),
+        status => match status.parse() {
+            Ok(status) => Err(state.exit(status)),
+            Err(_) => Err(diag::error(&format!("exit: invalid status `{}`", status)).into_error()),
+        },
+    })?;
+    Ok(())
+}
+
+fn arg(args: &[String], i: usize) -> &str {
+    args.get(i).map(|x| x.as_str()).unwrap_or("")
+}
@@ -0,0 +1,217 @@
+use std::{fs, process, ptr, slice};
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::CString;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Error, ErrorKind};
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::lib::path;
+use crate::lib::term;
+
+// RITE bytecode, cached not to parse mruby source on every run
+//
+//   <cfg_path>\cache\mrb\<alias>\<hash>.mrb    ```ruby blocks of alias
+//   <cfg_path>\cache\mrb\.shared\<hash>.mrb    prelude and user library
+//
+// cache of an alias is dropped when its .txt is newer than it,
+// shared one is keyed by source only, and cleared when it grows
+pub struct MrbCache {
+    dir: PathBuf,
+    shared: bool,
+}
+
+impl MrbCache {
+    // None if file is not on disk
+    pub fn new(file: &str, alias_name: &str) -> Option<MrbCache> {
+        let modified = fs::metadata(file).and_then(|x| x.modified()).ok()?;
+        let dir = PathBuf::from(path::cfg_cache_path().ok()?).join("mrb").join(alias_name);
+
+        // stale, .txt was edited after last store
+        let stored = fs::metadata(&dir).and_then(|x| x.modified());
+        if stored.map(|x| x < modified).unwrap_or(false) {
+            let _ = fs::remove_dir_all(&dir);
+        }
+        Some(MrbCache { dir, shared: false })
+    }
+
+    // alias names can't start with '.'
+    pub fn shared() -> Option<MrbCache> {
+        let dir = PathBuf::from(path::cfg_cache_path().ok()?).join("mrb").join(".shared");
+        Some(MrbCache { dir, shared: true })
+    }
+
+    // file: filename of debug info, empty for none
+    pub fn load(&self, source: &str, file: &str) -> Option<Vec<u8>> {
+        if !SUPPORTED.load(Ordering::SeqCst) {
+            return None;
+        }
+        fs::read(self.path(source, file)).ok()
+    }
+
+    // not stored on syntax error, or on other mruby version
+    pub fn store(&self, source: &str, file: &str) -> io::Result<()> {
+        // old sources of user library, and of other alias.exe versions
+        const MAX_SHARED: usize = 256;
+
+        let bytecode = compile(source, file)?;
+        if self.shared && fs::read_dir(&self.dir).map(|x| x.count() > MAX_SHARED).unwrap_or(false) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+        fs::create_dir_all(&self.dir)?;
+
+        // others may be loading it, e.g. git hooks in parallel
+        let path = self.path(source, file);
+        let tmp = path.with_extension(format!("{}.tmp", process::id()));
+        let result = fs::write(&tmp, &bytecode).and_then(|_| fs::rename(&tmp, &path));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    // broken, or built by other mruby
+    pub fn remove(&self, source: &str, file: &str) {
+        let _ = fs::remove_file(self.path(source, file));
+    }
+
+    fn path(&self, source: &str, file: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        file.hash(&mut hasher);
+        source.hash(&mut hasher);
+        self.dir.join(format!("{:016x}.mrb", hasher.finish()))
+    }
+}
+
+// ---
+
+// mruby linked by mrusty, which doesn't expose its compiler
+//
+// structs below are copied from headers of this version, the cache is
+// disabled for any other one, and blocks run from source as before
+const MRUBY_VERSION: &str = "1.2.0";
+
+static SUPPORTED: AtomicBool = AtomicBool::new(false);
+
+// version: MRUBY_VERSION of linked mruby, checked once per process
+pub fn check_version(version: &str) {
+    SUPPORTED.store(version == MRUBY_VERSION, Ordering::SeqCst);
+}
+
+type MrbState = c_void;
+type MrbcContext = c_void;
+type MrbIrep = c_void;
+
+// struct mrb_parser_state of include/mruby/compile.h, up to nerr
+#[repr(C)]
+struct MrbParserState {
+    _mrb: *mut c_void,
+    _pool: *mut c_void,
+    _cells: *mut c_void,
+    _s: *const c_char,
+    _send: *const c_char,
+    _f: *mut c_void,
+    _cxt: *mut c_void,
+    _filename: *const c_char,
+    _lineno: c_int,
+    _column: c_int,
+    _lstate: c_int,
+    _lex_strterm: *mut c_void,
+    _cond_stack: u32,
+    _cmdarg_stack: u32,
+    _paren_nest: c_int,
+    _lpar_beg: c_int,
+    _in_def: c_int,
+    _in_single: c_int,
+    _cmd_start: u8,
+    _locals: *mut c_void,
+    _pb: *mut c_void,
+    _buf: [c_char; 1024],
+    _bidx: c_int,
+    _all_heredocs: *mut c_void,
+    _heredocs_from_nextline: *mut c_void,
+    _parsing_heredoc: *mut c_void,
+    _lex_strterm_before_heredoc: *mut c_void,
+    _heredoc_end_now: u8,
+    _ylval: *mut c_void,
+    nerr: usize,
+}
+
+// struct RProc of include/mruby/proc.h, MRB_OBJECT_HEADER and body.irep
+#[repr(C)]
+struct RProc {
+    // tt:8, color:3, flags:21
+    header: u32,
+    _c: *mut c_void,
+    _gcnext: *mut c_void,
+    irep: *mut MrbIrep,
+}
+
+// enum mrb_vtype of include/mruby/value.h
+const MRB_TT_PROC: u32 = 13;
+
+extern "C" {
+    fn mrb_open() -> *mut MrbState;
+    fn mrb_close(mrb: *mut MrbState);
+    fn mrb_free(mrb: *mut MrbState, p: *mut c_void);
+    fn mrbc_context_new(mrb: *mut MrbState) -> *mut MrbcContext;
+    fn mrbc_context_free(mrb: *mut MrbState, c: *mut MrbcContext);
+    fn mrbc_filename(mrb: *mut MrbState, c: *mut MrbcContext, s: *const c_char) -> *const c_char;
+    fn mrb_parse_nstring(mrb: *mut MrbState, s: *const c_char, len: c_int, c: *mut MrbcContext) -> *mut MrbParserState;
+    fn mrb_parser_free(p: *mut MrbParserState);
+    fn mrb_generate_code(mrb: *mut MrbState, p: *mut MrbParserState) -> *mut RProc;
+    fn mrb_dump_irep(mrb: *mut MrbState, irep: *mut MrbIrep, flags: u8, bin: *mut *mut u8, bin_size: *mut usize) -> c_int;
+}
+
+// keep line numbers, for backtraces
+const DUMP_DEBUG_INFO: u8 = 1;
+const MRB_DUMP_OK: c_int = 0;
+
+// same as `mrbc -g`, in its own mrb_state
+pub fn compile(source: &str, filename: &str) -> io::Result<Vec<u8>> {
+    let filename = CString::new(filename)?;
+    let failed = || -> io::Result<Vec<u8>> {
+        Err(Error::new(ErrorKind::InvalidData, format!("{}: mruby can't compile block", term::ewrite("failed")?)))
+    };
+    if !SUPPORTED.load(Ordering::SeqCst) {
+        return failed();
+    }
+
+    unsafe {
+        let mrb = mrb_open();
+        if mrb.is_null() {
+            return failed();
+        }
+        let c = mrbc_context_new(mrb);
+        if !filename.as_bytes().is_empty() {
+            mrbc_filename(mrb, c, filename.as_ptr());
+        }
+
+        let p = mrb_parse_nstring(mrb, source.as_ptr() as *const c_char, source.len() as c_int, c);
+        // code of syntax error is an empty program, not an error
+        let proc_ = if p.is_null() || (*p).nerr > 0 { ptr::null_mut() } else { mrb_generate_code(mrb, p) };
+
+        let mut bytecode = None;
+        if !proc_.is_null() && (*proc_).header & 0xff == MRB_TT_PROC {
+            let mut bin: *mut u8 = ptr::null_mut();
+            let mut bin_size: usize = 0;
+            if mrb_dump_irep(mrb, (*proc_).irep, DUMP_DEBUG_INFO, &mut bin, &mut bin_size) == MRB_DUMP_OK {
+                bytecode = Some(slice::from_raw_parts(bin, bin_size).to_vec());
+                mrb_free(mrb, bin as *mut c_void);
+            }
+        }
+
+        if !p.is_null() {
+            mrb_parser_free(p);
+        }
+        mrbc_context_free(mrb, c);
+        mrb_close(mrb);
+
+        match bytecode {
+            Some(x) => Ok(x),
+            None => failed(),
+        }
+    }
+}
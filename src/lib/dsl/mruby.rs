@@ -1,31 +1,190 @@
 use std::io::{self, Error, ErrorKind};
-use std::{env, process};
+use std::{env, fs};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::path::Path;
 
+use regex::Regex;
 use mrusty::*;
 
+use crate::lib::path;
 use crate::lib::term;
 use crate::lib::cmd;
+use crate::lib::diag;
+use crate::lib::exec;
+use crate::lib::dsl::{self, mrbc, Context, ScriptEngine, ScriptFn, ScriptValue};
 
-pub fn mruby_new() -> Result<mrusty::MrubyType, mrusty::MrubyError> {
+// ScriptEngine of ```ruby blocks, and <%= %>
+pub struct MrubyEngine {
+    mruby: mrusty::MrubyType,
+    // bytecode of blocks, None if alias is not in a file
+    cache: Option<mrbc::MrbCache>,
+    // filename of debug info in it
+    file: String,
+    state: Rc<exec::RunState>,
+}
+
+impl MrubyEngine {
+    pub fn new(ctx: &Context) -> io::Result<MrubyEngine> {
+        // error, oh... ( help: the trait `std::marker::Sync` is not implemented for `std::rc::Rc<std::cell::RefCell<mrusty::mruby::Mruby>>` )
+        //lazy_static! {
+        //    static ref MRUBY: mrusty::MrubyType = mruby::mruby_new().unwrap();
+        //}
+        // built-in prelude, not by user
+        let mruby = mruby_new(ctx)
+            .map_err(|err| diag::error(&format!("mruby: {}", err)).hint("mruby prelude failed to load").into_error())?;
+        with_state(&ctx.state, || load_lib(&mruby, &ctx.state))?;
+        mruby.filename(ctx.file);
+
+        let cache = mrbc::MrbCache::new(ctx.file, ctx.alias_name());
+        let mut engine = MrubyEngine { mruby, cache, file: ctx.file.to_owned(), state: ctx.state.clone() };
+        dsl::def_runtime(&mut engine)?;
+        Ok(engine)
+    }
+
+    // without prelude and user library, only to check syntax
+    pub fn checker(ctx: &Context) -> MrubyEngine {
+        let mruby = mrusty::Mruby::new();
+        mruby.filename(ctx.file);
+        MrubyEngine { mruby, cache: None, file: ctx.file.to_owned(), state: ctx.state.clone() }
+    }
+}
+
+impl ScriptEngine for MrubyEngine {
+    // bytecode is cached, not to parse blocks on every run
+    fn eval(&mut self, source: &str) -> io::Result<()> {
+        let result = with_state(&self.state, || run_cached(&self.mruby, self.cache.as_ref(), &wrap_source(source), &self.file));
+        mruby_result(&self.state, result).map(|_| ())
+    }
+
+    fn eval_to_string(&mut self, source: &str) -> io::Result<String> {
+        let value = with_state(&self.state, || mruby_run(&self.state, &self.mruby, source))?;
+        value2str(&self.mruby, value)
+    }
+
+    fn check(&mut self, source: &str) -> io::Result<()> {
+        mruby_run(&self.state, &self.mruby, &format!("lambda do; {}\nend", source))?;
+        Ok(())
+    }
+
+    fn call_directive(&mut self, name: &str, args: &[String]) -> io::Result<()> {
+        let args: Vec<String> = args.iter().map(|x| ruby_str(x)).collect();
+        with_state(&self.state, || mruby_run(&self.state, &self.mruby, &format!("Alias.call_directive({}, [{}])", ruby_str(name), args.join(", "))))?;
+        Ok(())
+    }
+
+    // defined already wins, e.g. Kernel#exit, or in user library
+    fn def_fn(&mut self, name: &str, f: ScriptFn) -> io::Result<()> {
+        FNS.lock().unwrap().insert(name.to_owned(), f);
+        let source = format!(r#"
+        unless respond_to?(:{0}, true)
+          def {0}(*args); MrubyFn.call("{0}", args.map(&:to_s)); end
+        end
+        "#, name);
+        mruby_result(&self.state, run_cached(&self.mruby, SHARED.as_ref(), &wrap_source(&source), ""))?;
+        Ok(())
+    }
+}
+
+thread_local! {
+    // run calling into mruby now, for MrubyFn. methods of mrusty can't capture it
+    static STATE: RefCell<Option<Rc<exec::RunState>>> = const { RefCell::new(None) };
+}
+
+// f with state given to MrubyFn, the previous one is restored after it
+fn with_state<T>(state: &Rc<exec::RunState>, f: impl FnOnce() -> T) -> T {
+    let prev = STATE.with(|x| x.replace(Some(state.clone())));
+    let result = f();
+    STATE.with(|x| *x.borrow_mut() = prev);
+    result
+}
+
+lazy_static! {
+    // functions of ScriptEngine::def_fn, called through MrubyFn
+    static ref FNS: Mutex<HashMap<String, ScriptFn>> = Mutex::new(HashMap::new());
+    // bytecode of prelude and user library, the same for all aliases
+    static ref SHARED: Option<mrbc::MrbCache> = mrbc::MrbCache::shared();
+}
+
+fn mruby_new(ctx: &Context) -> Result<mrusty::MrubyType, mrusty::MrubyError> {
+    let args = ctx.args;
     let mruby = mrusty::Mruby::new();
+    mrbc::check_version(mruby.run("MRUBY_VERSION")?.to_str().unwrap_or(""));
     {
         mruby.def_class("MrubyInitialize")
             .def_const("ARGV", {
                 let mut argv: Vec<mrusty::Value> = Vec::new();
-                for x in env::args().skip(1) {
+                for x in args.iter().skip(1) {
                     argv.push(mruby.string(&x));
                 }
                 mruby.array(argv)
-            });
+            })
+            .def_const("PROGRAM_NAME", mruby.string(&args[0]))
+            .def_const("ALIAS_NAME", mruby.string(ctx.alias_name()));
+
+        // global const ARGV, ALIAS_NAME and $0
+        run_cached(&mruby, SHARED.as_ref(), r#"
+        ARGV = MrubyInitialize::ARGV
+        ALIAS_NAME = MrubyInitialize::ALIAS_NAME
+        $0 = MrubyInitialize::PROGRAM_NAME
+        "#, "")?;
+    }
+    {
+        setup_exit(&mruby);
+
+        // exit and abort, terminate alias with status
+        run_cached(&mruby, SHARED.as_ref(), r#"
+        class MrubyExit < Exception
+          attr_reader :status
+
+          def initialize(status)
+            super("exit")
+            @status = status
+          end
+        end
+
+        module Kernel
+          def exit(status = 0)
+            status = 0 if status == true
+            status = 1 if status == false
+            raise MrubyExit.new(status)
+          end
 
-        // global const ARGV
-        mruby.run("ARGV = MrubyInitialize::ARGV")?;
+          def abort(message = nil)
+            MrubyStderr.puts(message.to_s) if message
+            raise MrubyExit.new(1)
+          end
+        end
+        "#, "")?;
+    }
+    {
+        setup_env(&mruby);
+
+        // ENV, process environment shared with @set
+        run_cached(&mruby, SHARED.as_ref(), r#"
+        class MrubyEnvHash
+          def [](key); MrubyEnv.get(key.to_s); end
+          def []=(key, value); MrubyEnv.set(key.to_s, value.nil? ? nil : value.to_s); end
+          def fetch(key, default = nil); key?(key) ? self[key] : default; end
+          def key?(key); !self[key].nil?; end
+          alias include? key?
+          alias has_key? key?
+          def delete(key); value = self[key]; self[key] = nil; value; end
+          def keys; MrubyEnv.keys; end
+          def each; keys.each { |key| yield key, self[key] }; self; end
+          def to_h; h = {}; keys.each { |key| h[key] = self[key] }; h; end
+        end
+
+        ENV = MrubyEnvHash.new
+        "#, "")?;
     }
     {
         setup_array_cmd(&mruby);
 
         // array extension
-        mruby.run(r#"
+        run_cached(&mruby, SHARED.as_ref(), r#"
         require 'array_cmd'
         require 'array_cmd_deep'
 
@@ -51,12 +210,880 @@ pub fn mruby_new() -> Result<mrusty::MrubyType, mrusty::MrubyError> {
             MrubyArrayFromCmd.from_cmd cmd_args_string
           end
         end
-        "#)?;
+
+        # hash extension, {name: "x", force: true} => --name=x --force
+        class Hash
+          def to_flags
+            select { |k, v| v != false && v != nil }
+              .map { |k, v| v == true ? "--#{k}" : "--#{k}=#{v}" }
+              .to_cmd
+          end
+        end
+        "#, "")?;
+    }
+    {
+        setup_json(&mruby);
+
+        // JSON, objects are [:__json_object__, [key, value, ...]] between MrubyJson
+        run_cached(&mruby, SHARED.as_ref(), r#"
+        module JSON
+          class ParserError < StandardError; end
+
+          def self.parse(source)
+            from_tagged(MrubyJson.parse(source.to_s))
+          rescue RuntimeError => e
+            raise ParserError, e.message
+          end
+
+          def self.generate(obj)
+            MrubyJson.generate(to_tagged(obj), false)
+          end
+
+          def self.pretty_generate(obj)
+            MrubyJson.generate(to_tagged(obj), true)
+          end
+
+          def self.from_tagged(x)
+            return x unless x.is_a?(Array)
+            return x.map { |v| from_tagged(v) } unless x[0] == :__json_object__
+
+            h = {}
+            pairs = x[1]
+            i = 0
+            while i < pairs.size
+              h[pairs[i]] = from_tagged(pairs[i + 1])
+              i += 2
+            end
+            h
+          end
+
+          def self.to_tagged(x)
+            case x
+            when Hash
+              pairs = []
+              x.each { |k, v| pairs << k.to_s << to_tagged(v) }
+              [:__json_object__, pairs]
+            when Array then x.map { |v| to_tagged(v) }
+            when nil, true, false, Numeric, String then x
+            else x.to_s
+            end
+          end
+        end
+
+        class Object
+          def to_json(*)
+            JSON.generate(self)
+          end
+        end
+        "#, "")?;
     }
+    {
+        setup_regex(&mruby);
+
+        // Regexp by regex crate, /.../ literal calls Regexp.compile
+        //
+        // ^ and $ match at line boundaries as in ruby, $~ is set by match but not $1
+        run_cached(&mruby, SHARED.as_ref(), r#"
+        class RegexpError < StandardError; end
+
+        class Regexp
+          IGNORECASE = 1
+          EXTENDED = 2
+          MULTILINE = 4
+
+          attr_reader :source, :options, :pattern
+
+          def self.compile(*args)
+            new(*args)
+          end
+
+          def self.escape(s)
+            MrubyRegex.escape(s.to_s)
+          end
+
+          def initialize(source, options = nil, *)
+            @source = source.is_a?(Regexp) ? source.source : source.to_s
+            @options = case options
+                       when Numeric then options.to_i
+                       when String
+                         (options.include?("i") ? IGNORECASE : 0) |
+                           (options.include?("x") ? EXTENDED : 0) |
+                           (options.include?("m") ? MULTILINE : 0)
+                       else options ? IGNORECASE : 0
+                       end
+            flags = "m"
+            flags += "i" if @options & IGNORECASE != 0
+            flags += "x" if @options & EXTENDED != 0
+            flags += "s" if @options & MULTILINE != 0
+            @pattern = "(?#{flags})#{@source}"
+            @names = MrubyRegex.names(@pattern)
+          rescue RuntimeError => e
+            raise RegexpError, e.message
+          end
+
+          def match(str, pos = 0)
+            return $~ = nil if str.nil?
+            str = str.to_s
+            found = MrubyRegex.match(@pattern, str[pos..-1] || "")
+            $~ = found && MatchData.new(self, str, str[0, pos] + found[0], found[1], found[2..-1], @names)
+          end
+
+          def match?(str)
+            !str.nil? && !MrubyRegex.match(@pattern, str.to_s).nil?
+          end
+
+          def =~(str)
+            m = match(str)
+            m && m.pre_match.size
+          end
+
+          def ===(str)
+            str.is_a?(String) && match?(str)
+          end
+
+          def to_s
+            "/#{@source}/"
+          end
+          alias inspect to_s
+        end
+
+        class MatchData
+          attr_reader :regexp, :string, :pre_match, :post_match
+
+          def initialize(regexp, string, pre_match, post_match, captures, names)
+            @regexp = regexp
+            @string = string
+            @pre_match = pre_match
+            @post_match = post_match
+            @captures = captures
+            @names = names
+          end
+
+          def [](i)
+            return @captures[i] if i.is_a?(Integer)
+            n = @names.index(i.to_s)
+            raise IndexError, "undefined group name reference: #{i}" unless n
+            @captures[n]
+          end
+
+          def captures; @captures[1..-1]; end
+          def to_a; @captures.dup; end
+          def to_s; @captures[0]; end
+          def size; @captures.size; end
+          alias length size
+
+          def named_captures
+            h = {}
+            @names.each_with_index { |name, i| h[name] = @captures[i] if name }
+            h
+          end
+        end
+
+        class String
+          alias mrusty_sub sub
+          alias mrusty_gsub gsub
+          alias mrusty_split split
+
+          def =~(re)
+            raise TypeError, "wrong argument type String (expected Regexp)" if re.is_a?(String)
+            re =~ self
+          end
+
+          def match(re, pos = 0)
+            (re.is_a?(Regexp) ? re : Regexp.new(re.to_s)).match(self, pos)
+          end
+
+          def match?(re)
+            (re.is_a?(Regexp) ? re : Regexp.new(re.to_s)).match?(self)
+          end
+
+          def scan(re)
+            re = Regexp.new(Regexp.escape(re.to_s)) unless re.is_a?(Regexp)
+            parts = MrubyRegex.split(re.pattern, self)
+            found = []
+            i = 1
+            while i < parts.size
+              caps = parts[i]
+              item = caps.size > 1 ? caps[1..-1] : caps[0]
+              block_given? ? yield(item) : found << item
+              i += 2
+            end
+            block_given? ? self : found
+          end
+
+          def sub(pattern, *rep, &block)
+            return mrusty_sub(pattern, *rep, &block) unless pattern.is_a?(Regexp)
+            m = pattern.match(self)
+            return dup unless m
+            m.pre_match + (block ? block.call(m[0]).to_s : MrubyRegex.expand(rep[0].to_s, m.to_a)) + m.post_match
+          end
+
+          def gsub(pattern, *rep, &block)
+            return mrusty_gsub(pattern, *rep, &block) unless pattern.is_a?(Regexp)
+            parts = MrubyRegex.split(pattern.pattern, self)
+            s = parts[0].dup
+            i = 1
+            while i < parts.size
+              caps = parts[i]
+              s << (block ? block.call(caps[0]).to_s : MrubyRegex.expand(rep[0].to_s, caps))
+              s << parts[i + 1]
+              i += 2
+            end
+            s
+          end
+
+          def sub!(*args, &block)
+            s = sub(*args, &block)
+            s == self ? nil : replace(s)
+          end
+
+          def gsub!(*args, &block)
+            s = gsub(*args, &block)
+            s == self ? nil : replace(s)
+          end
+
+          def split(pattern = nil, limit = 0)
+            return mrusty_split(pattern, limit) unless pattern.is_a?(Regexp)
+            parts = MrubyRegex.split(pattern.pattern, self)
+            fields = []
+            i = 0
+            while i < parts.size
+              fields << parts[i]
+              i += 2
+            end
+            fields.pop while limit == 0 && fields.last == ""
+            fields
+          end
+        end
+        "#, "")?;
+    }
+    {
+        setup_file(&mruby);
+
+        // File and Dir, not in mruby core
+        run_cached(&mruby, SHARED.as_ref(), r#"
+        class File
+          def self.read(path); MrubyFile.read(path.to_s); end
+          def self.write(path, data); MrubyFile.write(path.to_s, data.to_s); end
+          def self.exist?(path); MrubyFile.exist(path.to_s); end
+          def self.file?(path); MrubyFile.kind(path.to_s) == "file"; end
+          def self.directory?(path); MrubyFile.kind(path.to_s) == "directory"; end
+          def self.glob(pattern); MrubyFile.glob(pattern.to_s); end
+        end
+
+        class Dir
+          def self.pwd; MrubyFile.pwd; end
+          def self.glob(pattern); File.glob(pattern); end
+          def self.[](pattern); File.glob(pattern); end
+        end
+        "#, "")?;
+    }
+
+    setup_fn(&mruby);
+    def_alias_module(&mruby, ctx)?;
 
     Ok(mruby)
 }
 
+// run source, exit(n) and exceptions are turned into io::Error
+pub fn mruby_run(state: &exec::RunState, mruby: &mrusty::MrubyType, source: &str) -> io::Result<mrusty::Value> {
+    mruby_result(state, mruby.run(&wrap_source(source)))
+}
+
+// same as mruby.run, by bytecode stored when it was parsed before
+//
+// file: filename of debug info, the one set by mruby.filename
+fn run_cached(mruby: &mrusty::MrubyType, cache: Option<&mrbc::MrbCache>, source: &str, file: &str) -> Result<mrusty::Value, mrusty::MrubyError> {
+    let cache = match cache {
+        Some(x) => x,
+        None => return mruby.run(source),
+    };
+
+    if let Some(bytecode) = cache.load(source, file) {
+        match mruby.runb(&bytecode) {
+            Ok(x) => return Ok(x),
+            Err(_) => cache.remove(source, file),
+        }
+    }
+
+    let result = mruby.run(source);
+    // parsed, even if raised. not cached is just slower
+    if result.is_ok() {
+        let _ = cache.store(source, file);
+    }
+    result
+}
+
+// source starts at this line of the wrapper
+const OFFSET: usize = 2;
+
+// exit(n) and exceptions are returned as [status, ...]
+fn wrap_source(source: &str) -> String {
+    lazy_static! {
+        static ref RE_END: Regex = Regex::new(r"(?m)^__END__\r?$").unwrap();
+    }
+    // rest of source is data, it can't be in begin ... end
+    let source = match RE_END.find(source) {
+        Some(m) => &source[..m.start()],
+        None => source,
+    };
+
+    format!(r#"begin
+  [0, begin
+{}
+  end]
+rescue MrubyExit => e
+  [1, e.status]
+rescue Exception => e
+  [2, e.class.to_s, e.message, e.backtrace || []]
+end"#, source)
+}
+
+// result of wrap_source, by run or runb
+fn mruby_result(state: &exec::RunState, result: Result<mrusty::Value, mrusty::MrubyError>) -> io::Result<mrusty::Value> {
+    lazy_static! {
+        // <file>:<line>:[<col>:] <message>
+        static ref RE_MRUBY_ERROR: Regex = Regex::new(r"(?s)^[^\n]*?:(\d+):(?:\d+:)?\s*(.*)$").unwrap();
+        // <file>:<line>[:in <method>]
+        static ref RE_BACKTRACE: Regex = Regex::new(r"^(.*?):(\d+)(.*)$").unwrap();
+    }
+
+    if let Err(err) = result {
+        // syntax error, before running
+        let msg = match err {
+            mrusty::MrubyError::Runtime(ref msg) => msg.to_owned(),
+            _ => err.to_string(),
+        };
+        if let Some(caps) = RE_MRUBY_ERROR.captures(&msg) {
+            let line: usize = caps.get(1).unwrap().as_str().parse().unwrap();
+            return Err(diag::error(&format!("mruby: {}", caps.get(2).unwrap().as_str()))
+                .line(line.saturating_sub(OFFSET + 1))
+                .into_error());
+        }
+        return Err(diag::error(&format!("mruby: {}", msg)).into_error());
+    }
+
+    let result = result.unwrap().to_vec().unwrap();
+    match result[0].to_i32().unwrap() {
+        0 => Ok(result[1].clone()),
+        1 => {
+            Err(state.exit(result[1].to_i32().unwrap_or(1)))
+        },
+        _ => {
+            let class = result[1].to_str().unwrap_or("Exception").to_owned();
+            let message = result[2].to_str().unwrap_or("").to_owned();
+            let backtrace: Vec<String> = result[3].to_vec().unwrap_or_default().iter()
+                .filter_map(|x| x.to_str().ok().map(|x| x.to_owned()))
+                .collect();
+
+            // outermost frame is this source, others may be in library
+            let file = backtrace.last()
+                .and_then(|x| RE_BACKTRACE.captures(x))
+                .map(|caps| caps[1].to_owned());
+            let mut line = None;
+            let mut diag_backtrace = Vec::new();
+            for x in &backtrace {
+                match RE_BACKTRACE.captures(x) {
+                    Some(ref caps) if Some(&caps[1]) == file.as_deref() => {
+                        let n = caps[2].parse::<usize>().unwrap().saturating_sub(OFFSET);
+                        line = line.or(Some(n));
+                        diag_backtrace.push(format!("{}:{}{}", &caps[1], n, &caps[3]));
+                    },
+                    _ => diag_backtrace.push(x.to_owned()),
+                }
+            }
+
+            let mut diag = diag::error(&format!("mruby: {}: {}", class, message));
+            if let Some(line) = line {
+                diag = diag.line(line.saturating_sub(1));
+            }
+            if diag_backtrace.len() > 1 {
+                diag = diag.note(&format!("backtrace:\n      {}", diag_backtrace.join("\n      ")));
+            }
+            Err(diag.into_error())
+        },
+    }
+}
+
+// user library, <cfg_path>/lib/*.rb, loaded before alias
+//
+// require 'name' in library or alias resolves into it,
+// libraries are loaded in order of their requires
+pub fn load_lib(mruby: &mrusty::MrubyType, state: &exec::RunState) -> io::Result<()> {
+    let lib_dir = path::cfg_lib_path()?;
+    if !Path::new(&lib_dir).is_dir() {
+        return Ok(());
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&lib_dir)? {
+        let path = entry?.path();
+        if path.extension().map(|x| x == "rb").unwrap_or(false) {
+            if let Some(name) = path.file_stem().and_then(|x| x.to_str()) {
+                names.push(name.to_owned());
+            }
+        }
+    }
+    names.sort();
+
+    mruby.def_class("MrubyLib")
+        .def_const("NAMES", mruby.array(names.iter().map(|x| mruby.string(x)).collect()));
+    mruby_result(state, run_cached(mruby, SHARED.as_ref(), &wrap_source(r#"
+    module Kernel
+      alias mrusty_require require
+
+      # already loaded if in user library
+      def require(name)
+        MrubyLib::NAMES.include?(name.to_s) ? false : mrusty_require(name)
+      end
+    end
+    "#), ""))?;
+
+    let mut loaded = HashSet::new();
+    for name in &names {
+        load_rb(mruby, state, &lib_dir, name, &names, &mut loaded)?;
+    }
+    Ok(())
+}
+
+fn load_rb(mruby: &mrusty::MrubyType, state: &exec::RunState, lib_dir: &str, name: &str, names: &Vec<String>, loaded: &mut HashSet<String>) -> io::Result<()> {
+    lazy_static! {
+        static ref RE_REQUIRE: Regex = Regex::new(r#"(?m)^\s*require\s*\(?\s*['"]([^'"]+)['"]"#).unwrap();
+    }
+
+    if !loaded.insert(name.to_owned()) {
+        return Ok(());
+    }
+
+    let lib_rb = format!("{}\\{}.rb", lib_dir, name);
+    let source = fs::read_to_string(&lib_rb)?;
+    for caps in RE_REQUIRE.captures_iter(&source) {
+        if names.contains(&caps[1].to_owned()) {
+            load_rb(mruby, state, lib_dir, &caps[1], names, loaded)?;
+        }
+    }
+
+    mruby.filename(&lib_rb);
+    mruby_result(state, run_cached(mruby, SHARED.as_ref(), &wrap_source(&source), &lib_rb))
+        .map_err(|err| diag::locate(err, &lib_rb, 1, &source))?;
+    Ok(())
+}
+
+fn setup_exit(mruby: &mrusty::MrubyType) {
+    mruby_class!(mruby.clone(), "MrubyStderr", {
+        def_self!("puts", |mruby, _slf: Value, message: Value| {
+            eprintln!("{}", message.to_str().unwrap_or(""));
+            mruby.nil()
+        });
+    });
+}
+
+fn setup_fn(mruby: &mrusty::MrubyType) {
+    mruby_class!(mruby.clone(), "MrubyFn", {
+        def_self!("call", |mruby, _slf: Value, name: Value, args: Value| {
+            let f = name.to_str().ok().and_then(|name| FNS.lock().unwrap().get(name).cloned());
+            let state = STATE.with(|x| x.borrow().clone());
+            let args: Vec<String> = args.to_vec().unwrap_or_default().iter()
+                .filter_map(|x| x.to_str().ok().map(|x| x.to_owned()))
+                .collect();
+            match f.and_then(|f| state.map(|state| f(&state, &args))) {
+                Some(Ok(ScriptValue::Int(i))) => mruby.fixnum(i as i32),
+                Some(Ok(ScriptValue::Str(s))) => mruby.string(&s),
+                Some(Ok(ScriptValue::Nil)) | None => mruby.nil(),
+                Some(Err(err)) => raise(&mruby, err),
+            }
+        });
+    });
+}
+
+fn setup_env(mruby: &mrusty::MrubyType) {
+    mruby_class!(mruby.clone(), "MrubyEnv", {
+        def_self!("get", |mruby, _slf: Value, key: Value| {
+            match key.to_str().ok().and_then(|key| env::var(key).ok()) {
+                Some(value) => mruby.string(&value),
+                None => mruby.nil(),
+            }
+        });
+        def_self!("set", |mruby, _slf: Value, key: Value, value: Value| {
+            if let Ok(key) = key.to_str() {
+                match value.to_str() {
+                    Ok(value) => env::set_var(key, value),
+                    // nil
+                    Err(_) => env::remove_var(key),
+                }
+            }
+            mruby.nil()
+        });
+        def_self!("keys", |mruby, _slf: Value| {
+            mruby.array(env::vars().map(|(key, _)| mruby.string(&key)).collect())
+        });
+    });
+}
+
+// tag of JSON object, see JSON.from_tagged
+const JSON_OBJECT: &str = "__json_object__";
+
+fn setup_json(mruby: &mrusty::MrubyType) {
+    mruby_class!(mruby.clone(), "MrubyJson", {
+        def_self!("parse", |mruby, _slf: Value, source: Value| {
+            let result = source.to_str().map_err(to_io_error)
+                .and_then(|source| serde_json::from_str(source).map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string())));
+            match result {
+                Ok(json) => json2value(&mruby, &json),
+                Err(err) => raise(&mruby, err),
+            }
+        });
+        def_self!("generate", |mruby, _slf: Value, value: Value, pretty: Value| {
+            let result = value2json(value).and_then(|json| {
+                let s = if pretty.to_bool().unwrap_or(false) { serde_json::to_string_pretty(&json) } else { serde_json::to_string(&json) };
+                s.map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))
+            });
+            match result {
+                Ok(s) => mruby.string(&s),
+                Err(err) => raise(&mruby, err),
+            }
+        });
+    });
+}
+
+fn json2value(mruby: &mrusty::MrubyType, json: &serde_json::Value) -> Value {
+    use serde_json::Value as Json;
+
+    match json {
+        Json::Null => mruby.nil(),
+        Json::Bool(b) => mruby.bool(*b),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) if i >= i32::MIN as i64 && i <= i32::MAX as i64 => mruby.fixnum(i as i32),
+            // beyond fixnum, written back as integer by value2json
+            _ => mruby.float(n.as_f64().unwrap_or(0.0)),
+        },
+        Json::String(s) => mruby.string(s),
+        Json::Array(a) => mruby.array(a.iter().map(|x| json2value(mruby, x)).collect()),
+        Json::Object(o) => {
+            let mut pairs = Vec::new();
+            for (key, value) in o {
+                pairs.push(mruby.string(key));
+                pairs.push(json2value(mruby, value));
+            }
+            mruby.array(vec![mruby.symbol(JSON_OBJECT), mruby.array(pairs)])
+        },
+    }
+}
+
+fn value2json(value: mrusty::Value) -> io::Result<serde_json::Value> {
+    use serde_json::Value as Json;
+
+    match parse_mruby_value(value)? {
+        MrubyValue::Nil => Ok(Json::Null),
+        MrubyValue::Str(s) | MrubyValue::Symbol(s) => Ok(Json::String(s)),
+        MrubyValue::I32(i) => Ok(Json::from(i)),
+        // integer beyond fixnum(i32), parsed into float by json2value
+        MrubyValue::F64(f) if f.fract() == 0.0 && f.abs() > i32::MAX as f64 && f.abs() <= (1u64 << 53) as f64 => Ok(Json::from(f as i64)),
+        MrubyValue::F64(f) => Ok(Json::from(f)),
+        MrubyValue::Bool(b) => Ok(Json::Bool(b)),
+        MrubyValue::Array(a) | MrubyValue::ArrayCmd(a) | MrubyValue::ArrayCmdDeep(a) => {
+            let tagged = a.len() == 2 && match parse_mruby_value(a[0].clone())? {
+                MrubyValue::Symbol(s) => s == JSON_OBJECT,
+                _ => false,
+            };
+            if !tagged {
+                return Ok(Json::Array(a.into_iter().map(value2json).collect::<io::Result<Vec<Json>>>()?));
+            }
+
+            let pairs = a[1].to_vec().map_err(to_io_error)?;
+            let mut o = serde_json::Map::new();
+            for pair in pairs.chunks(2) {
+                if pair.len() != 2 {
+                    return Err(Error::new(ErrorKind::InvalidData, "JSON object needs key and value pairs"));
+                }
+                let key = pair[0].to_str().map_err(to_io_error)?.to_owned();
+                o.insert(key, value2json(pair[1].clone())?);
+            }
+            Ok(Json::Object(o))
+        },
+    }
+}
+
+fn setup_regex(mruby: &mrusty::MrubyType) {
+    mruby_class!(mruby.clone(), "MrubyRegex", {
+        // [<pre_match>, <post_match>, <capture 0>, <capture 1>, ...], or nil
+        def_self!("match", |mruby, _slf: Value, pattern: Value, s: Value| {
+            let result = pattern.to_str().and_then(|pattern| s.to_str().map(|s| (pattern, s)))
+                .map_err(to_io_error)
+                .and_then(|(pattern, s)| Ok((compile_regex(pattern)?, s)));
+            match result {
+                Ok((re, s)) => match re.captures(s) {
+                    Some(caps) => {
+                        let m = caps.get(0).unwrap();
+                        let mut a = vec![mruby.string(&s[..m.start()]), mruby.string(&s[m.end()..])];
+                        a.append(&mut captures2value(&mruby, &caps));
+                        mruby.array(a)
+                    },
+                    None => mruby.nil(),
+                },
+                Err(err) => raise(&mruby, err),
+            }
+        });
+        // [<text>, <captures>, <text>, <captures>, ..., <text>]
+        def_self!("split", |mruby, _slf: Value, pattern: Value, s: Value| {
+            let result = pattern.to_str().and_then(|pattern| s.to_str().map(|s| (pattern, s)))
+                .map_err(to_io_error)
+                .and_then(|(pattern, s)| Ok((compile_regex(pattern)?, s)));
+            match result {
+                Ok((re, s)) => {
+                    let mut a = Vec::new();
+                    let mut cur = 0;
+                    for caps in re.captures_iter(s) {
+                        let m = caps.get(0).unwrap();
+                        a.push(mruby.string(&s[cur..m.start()]));
+                        a.push(mruby.array(captures2value(&mruby, &caps)));
+                        cur = m.end();
+                    }
+                    a.push(mruby.string(&s[cur..]));
+                    mruby.array(a)
+                },
+                Err(err) => raise(&mruby, err),
+            }
+        });
+        // group names, nil if unnamed
+        def_self!("names", |mruby, _slf: Value, pattern: Value| {
+            match pattern.to_str().map_err(to_io_error).and_then(compile_regex) {
+                Ok(re) => mruby.array(re.capture_names().map(|x| x.map(|x| mruby.string(x)).unwrap_or(mruby.nil())).collect()),
+                Err(err) => raise(&mruby, err),
+            }
+        });
+        def_self!("escape", |mruby, _slf: Value, s: Value| {
+            mruby.string(&regex::escape(s.to_str().unwrap_or("")))
+        });
+        // \0 .. \9 in replacement
+        def_self!("expand", |mruby, _slf: Value, replacement: Value, caps: Value| {
+            let caps: Vec<Option<String>> = caps.to_vec().unwrap_or_default().iter()
+                .map(|x| x.to_str().ok().map(|x| x.to_owned()))
+                .collect();
+            mruby.string(&expand(replacement.to_str().unwrap_or(""), &caps))
+        });
+    });
+}
+
+// compiled once
+fn compile_regex(pattern: &str) -> io::Result<Regex> {
+    lazy_static! {
+        static ref CACHE: Mutex<HashMap<String, Regex>> = Mutex::new(HashMap::new());
+    }
+
+    let mut cache = CACHE.lock().unwrap();
+    if let Some(re) = cache.get(pattern) {
+        return Ok(re.clone());
+    }
+    match Regex::new(pattern) {
+        Ok(re) => {
+            cache.insert(pattern.to_owned(), re.clone());
+            Ok(re)
+        },
+        Err(err) => Err(Error::new(ErrorKind::InvalidInput, err.to_string())),
+    }
+}
+
+fn captures2value(mruby: &mrusty::MrubyType, caps: &regex::Captures) -> Vec<Value> {
+    caps.iter().map(|x| x.map(|x| mruby.string(x.as_str())).unwrap_or(mruby.nil())).collect()
+}
+
+fn expand(replacement: &str, caps: &[Option<String>]) -> String {
+    let mut s = String::new();
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.peek().cloned() {
+                Some(d) if d.is_ascii_digit() => {
+                    let i = d.to_digit(10).unwrap() as usize;
+                    s.push_str(caps.get(i).and_then(|x| x.as_ref()).map(|x| x.as_str()).unwrap_or(""));
+                    chars.next();
+                    continue;
+                },
+                Some('\\') => {
+                    s.push('\\');
+                    chars.next();
+                    continue;
+                },
+                _ => (),
+            }
+        }
+        s.push(c);
+    }
+    s
+}
+
+fn setup_file(mruby: &mrusty::MrubyType) {
+    mruby_class!(mruby.clone(), "MrubyFile", {
+        def_self!("read", |mruby, _slf: Value, path: Value| {
+            match path.to_str().map_err(to_io_error).and_then(|path| file_error(path, fs::read_to_string(path))) {
+                Ok(s) => mruby.string(&s),
+                Err(err) => raise(&mruby, err),
+            }
+        });
+        def_self!("write", |mruby, _slf: Value, path: Value, data: Value| {
+            let result = path.to_str().and_then(|path| data.to_str().map(|data| (path, data)))
+                .map_err(to_io_error)
+                .and_then(|(path, data)| file_error(path, fs::write(path, data)).map(|_| data.len()));
+            match result {
+                Ok(len) => mruby.fixnum(len as i32),
+                Err(err) => raise(&mruby, err),
+            }
+        });
+        def_self!("exist", |mruby, _slf: Value, path: Value| {
+            mruby.bool(path.to_str().map(|path| Path::new(path).exists()).unwrap_or(false))
+        });
+        // "file", "directory", or nil
+        def_self!("kind", |mruby, _slf: Value, path: Value| {
+            match path.to_str().ok().and_then(|path| fs::metadata(path).ok()) {
+                Some(ref meta) if meta.is_dir() => mruby.string("directory"),
+                Some(_) => mruby.string("file"),
+                None => mruby.nil(),
+            }
+        });
+        def_self!("glob", |mruby, _slf: Value, pattern: Value| {
+            let result = pattern.to_str().map_err(to_io_error)
+                .and_then(|pattern| glob::glob(pattern).map_err(|err| Error::new(ErrorKind::InvalidInput, format!("{}: {}", pattern, err))));
+            match result {
+                Ok(paths) => {
+                    let mut paths: Vec<String> = paths.filter_map(|x| x.ok())
+                        .filter_map(|x| x.to_str().map(|x| x.to_owned()))
+                        .collect();
+                    paths.sort();
+                    mruby.array(paths.iter().map(|x| mruby.string(x)).collect())
+                },
+                Err(err) => raise(&mruby, err),
+            }
+        });
+        def_self!("pwd", |mruby, _slf: Value| {
+            match env::current_dir() {
+                Ok(dir) => mruby.string(&dir.to_string_lossy()),
+                Err(err) => raise(&mruby, err),
+            }
+        });
+    });
+}
+
+// with path, e.g. "foo.txt: No such file or directory"
+fn file_error<T>(path: &str, result: io::Result<T>) -> io::Result<T> {
+    result.map_err(|err| Error::new(err.kind(), format!("{}: {}", path, err)))
+}
+
+// Alias module, runtime of alias for mruby blocks
+//
+//   Alias.run("git status")           # => exit status
+//   Alias.capture("git rev-parse HEAD") # => .stdout, .stderr, .status
+//   Alias.setenv("KEY", "value")      # same as @set
+//   Alias.pushd("dir"), Alias.popd    # same as @pushd, @popd
+//   Alias.name, Alias.args
+//
+//   # @venv <path> in alias, args are split like cmd
+//   Alias.directive(:venv, usage: "<path>", args: 1) { |args| ... }
+fn def_alias_module(mruby: &mrusty::MrubyType, ctx: &Context) -> Result<(), mrusty::MrubyError> {
+    mruby.def_class("AliasInitialize")
+        .def_const("NAME", mruby.string(ctx.alias_name()))
+        .def_const("ARGS", mruby.array(ctx.args.iter().skip(1).map(|x| mruby.string(x)).collect()));
+
+    mruby_class!(mruby.clone(), "AliasRuntime", {
+        def_self!("run", |mruby, _slf: Value, cmd: Value| {
+            match cmd.to_str().map_err(to_io_error).and_then(cmd::command_status) {
+                Ok(status) => mruby.fixnum(status),
+                Err(err) => raise(&mruby, err),
+            }
+        });
+        def_self!("capture", |mruby, _slf: Value, cmd: Value| {
+            match cmd.to_str().map_err(to_io_error).and_then(cmd::command_capture) {
+                Ok((stdout, stderr, status)) => mruby.array(vec![mruby.string(&stdout), mruby.string(&stderr), mruby.fixnum(status)]),
+                Err(err) => raise(&mruby, err),
+            }
+        });
+        // max_args < 0 is unlimited
+        def_self!("directive", |mruby, _slf: Value, name: Value, usage: Value, min_args: Value, max_args: Value| {
+            let result = name.to_str().and_then(|name| usage.to_str().map(|usage| (name, usage)))
+                .and_then(|x| min_args.to_i32().map(|min| (x, min)))
+                .and_then(|x| max_args.to_i32().map(|max| (x, max)))
+                .map_err(to_io_error)
+                .and_then(|(((name, usage), min), max)| exec::register_directive(name, exec::Directive {
+                    lang: "ruby".to_owned(),
+                    usage: usage.to_owned(),
+                    min_args: min.max(0) as usize,
+                    max_args: if max < 0 { None } else { Some(max as usize) },
+                }));
+            match result {
+                Ok(_) => mruby.nil(),
+                Err(err) => raise(&mruby, err),
+            }
+        });
+    });
+
+    run_cached(mruby, SHARED.as_ref(), r#"
+    module Alias
+      NAME = AliasInitialize::NAME
+      ARGS = AliasInitialize::ARGS
+
+      class Captured
+        attr_reader :stdout, :stderr, :status
+
+        def initialize(stdout, stderr, status)
+          @stdout = stdout
+          @stderr = stderr
+          @status = status
+        end
+
+        def success?
+          @status == 0
+        end
+      end
+
+      def self.name; NAME; end
+      def self.args; ARGS; end
+      def self.run(cmd); AliasRuntime.run(cmd); end
+      def self.capture(cmd); Captured.new(*AliasRuntime.capture(cmd)); end
+      # by runtime of dsl, sharing state of the run
+      def self.setenv(key, value); MrubyFn.call("setenv", [key.to_s, value.to_s]); end
+      def self.pushd(path); MrubyFn.call("pushd", [path.to_s]); end
+      def self.popd; MrubyFn.call("popd", []); end
+
+      DIRECTIVES = {}
+
+      # args: count or range of args, any by default
+      def self.directive(name, opts = {}, &block)
+        raise ArgumentError, "Alias.directive needs a block" unless block
+        name = name.to_s
+        name = name[1..-1] if name[0] == "@"
+        min, max = case opts[:args]
+                   when nil then [0, -1]
+                   when Range then [opts[:args].first, opts[:args].exclude_end? ? opts[:args].last - 1 : opts[:args].last]
+                   else [opts[:args].to_i, opts[:args].to_i]
+                   end
+        AliasRuntime.directive(name, (opts[:usage] || "[args...]").to_s, min, max)
+        DIRECTIVES[name] = block
+        nil
+      end
+
+      def self.call_directive(name, args)
+        DIRECTIVES[name].call(args)
+      end
+    end
+    "#, "")?;
+
+    Ok(())
+}
+
+// single-quoted ruby string literal
+fn ruby_str(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn to_io_error(err: mrusty::MrubyError) -> io::Error {
+    Error::new(ErrorKind::InvalidInput, err.to_string())
+}
+
+// io error to RuntimeError, without color
+fn raise(mruby: &mrusty::MrubyType, err: io::Error) -> Value {
+    mruby.raise("RuntimeError", diag::from_error(err).message())
+}
+
 struct ArrayCmd {
     value: mrusty::Value
 }
@@ -89,8 +1116,7 @@ fn setup_array_cmd(mruby: &mrusty::MrubyType) {
         def_self!("from_cmd", |mruby, _slf: Value, cmd_args_string: Value| {
             let cmd_args_string = cmd_args_string.to_str();
             if let Err(err) = cmd_args_string {
-                eprintln!("{}: in Array.from_cmd: {}", term::ewrite("mruby failed").unwrap(), err);
-                process::exit(1);
+                return mruby.raise("ArgumentError", &format!("Array.from_cmd: {}", err));
             }
 
             let a = cmd::split_args(cmd_args_string.unwrap()).iter()
@@ -115,7 +1141,10 @@ fn cmdstr2value(mruby: &mrusty::MrubyType, s: &str) -> Value {
 }
 
 enum MrubyValue {
+    Nil,
     Str(String),
+    // bare word
+    Symbol(String),
     I32(i32),
     F64(f64),
     Bool(bool),
@@ -126,7 +1155,9 @@ enum MrubyValue {
 
 pub fn value2str(mruby: &mrusty::MrubyType, value: mrusty::Value) -> io::Result<String> {
     match parse_mruby_value(value)? {
+        MrubyValue::Nil => Ok(String::new()),
         MrubyValue::Str(s) => Ok(format!(r#""{}""#, s)),
+        MrubyValue::Symbol(s) => Ok(s),
         MrubyValue::I32(i) => Ok(i.to_string()),
         MrubyValue::F64(f) => Ok(f.to_string()),
         MrubyValue::Bool(b) => Ok(b.to_string()),
@@ -172,6 +1203,10 @@ pub fn value2str(mruby: &mrusty::MrubyType, value: mrusty::Value) -> io::Result<
 }
 
 fn parse_mruby_value(value: mrusty::Value) -> io::Result<MrubyValue> {
+    // before to_bool, nil is false in mruby
+    if let Ok(true) = value.call("nil?", vec![]).and_then(|x| x.to_bool()) {
+        return Ok(MrubyValue::Nil);
+    }
     if let Ok(s) = value.to_str() {
         return Ok(MrubyValue::Str(s.to_owned()));
     }
@@ -196,5 +1231,25 @@ fn parse_mruby_value(value: mrusty::Value) -> io::Result<MrubyValue> {
         return Ok(MrubyValue::ArrayCmdDeep(inst.value.to_vec().unwrap()));
     }
 
-    Err(Error::new(ErrorKind::InvalidData, format!("{}: unknown value type", term::ewrite("mruby failed")?)))
+    let class_name = value.call("class", vec![])
+        .and_then(|x| x.call("to_s", vec![]))
+        .and_then(|x| x.to_str().map(|x| x.to_owned()));
+    let converted = match class_name.as_ref().map(|x| x.as_str()) {
+        // (1..3) => 1 2 3
+        Ok("Range") => value.call("to_a", vec![]).and_then(|x| x.call("to_cmd", vec![])),
+        Ok("Hash") => value.call("to_flags", vec![]),
+        Ok("Symbol") => {
+            let s = value.call("to_s", vec![]).and_then(|x| x.to_str().map(|x| x.to_owned()));
+            return match s {
+                Ok(s) => Ok(MrubyValue::Symbol(s)),
+                Err(err) => Err(Error::new(ErrorKind::InvalidData, format!("{}: {}", term::ewrite("mruby failed")?, err))),
+            };
+        },
+        // any object, by to_s
+        _ => value.call("to_s", vec![]),
+    };
+    match converted {
+        Ok(converted) => parse_mruby_value(converted),
+        Err(err) => Err(Error::new(ErrorKind::InvalidData, format!("{}: unknown value type: {}", term::ewrite("mruby failed")?, err))),
+    }
 }
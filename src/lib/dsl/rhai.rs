@@ -0,0 +1,103 @@
+use std::io;
+use std::rc::Rc;
+
+use regex::Regex;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Position, Scope};
+
+use crate::lib::diag;
+use crate::lib::exec;
+use crate::lib::dsl::{self, Context, ScriptEngine, ScriptFn, ScriptValue};
+
+// ScriptEngine of ```rhai blocks
+//
+//   let name = if ARGV.len() > 0 { ARGV[0] } else { "world" };
+//   run(`echo hello ${name}`);
+//
+// ARGV, ALIAS_NAME and Alias runtime(see dsl::def_runtime) are defined,
+// variables are kept across blocks
+pub struct RhaiEngine {
+    engine: Engine,
+    scope: Scope<'static>,
+    state: Rc<exec::RunState>,
+}
+
+impl RhaiEngine {
+    pub fn new(ctx: &Context) -> io::Result<RhaiEngine> {
+        let mut scope = Scope::new();
+        let argv: Array = ctx.args.iter().skip(1).map(|x| Dynamic::from(x.clone())).collect();
+        scope.push_constant("ARGV", argv);
+        scope.push_constant("ALIAS_NAME", ctx.alias_name().to_owned());
+
+        let mut rhai = RhaiEngine { engine: Engine::new(), scope, state: ctx.state.clone() };
+        dsl::def_runtime(&mut rhai)?;
+        Ok(rhai)
+    }
+}
+
+impl ScriptEngine for RhaiEngine {
+    fn eval(&mut self, source: &str) -> io::Result<()> {
+        self.engine.run_with_scope(&mut self.scope, source)
+            .map_err(|err| to_io_error(&err.to_string(), err.position()))
+    }
+
+    fn eval_to_string(&mut self, source: &str) -> io::Result<String> {
+        let value = self.engine.eval_with_scope::<Dynamic>(&mut self.scope, source)
+            .map_err(|err| to_io_error(&err.to_string(), err.position()))?;
+        Ok(value2str(&value))
+    }
+
+    fn check(&mut self, source: &str) -> io::Result<()> {
+        self.engine.compile_with_scope(&self.scope, source)
+            .map(|_| ())
+            .map_err(|err| to_io_error(&err.to_string(), err.position()))
+    }
+
+    fn def_fn(&mut self, name: &str, f: ScriptFn) -> io::Result<()> {
+        let state = self.state.clone();
+        let call = Rc::new(move |args: Vec<Dynamic>| -> Result<Dynamic, Box<EvalAltResult>> {
+            let args: Vec<String> = args.iter().map(|x| x.to_string()).collect();
+            match f(&state, &args) {
+                Ok(ScriptValue::Nil) => Ok(Dynamic::UNIT),
+                Ok(ScriptValue::Int(i)) => Ok(Dynamic::from(i)),
+                Ok(ScriptValue::Str(s)) => Ok(Dynamic::from(s)),
+                // without color
+                Err(err) => Err(diag::from_error(err).message().into()),
+            }
+        });
+
+        // functions have fixed arity in rhai, up to 3 arguments
+        let (call0, call1, call2, call3) = (call.clone(), call.clone(), call.clone(), call);
+        self.engine.register_fn(name, move || call0(vec![]));
+        self.engine.register_fn(name, move |a: Dynamic| call1(vec![a]));
+        self.engine.register_fn(name, move |a: Dynamic, b: Dynamic| call2(vec![a, b]));
+        self.engine.register_fn(name, move |a: Dynamic, b: Dynamic, c: Dynamic| call3(vec![a, b, c]));
+        Ok(())
+    }
+}
+
+fn to_io_error(message: &str, pos: Position) -> io::Error {
+    lazy_static! {
+        // position is located by diag
+        static ref RE_POSITION: Regex = Regex::new(r"\s*\(line \d+, position \d+\)$").unwrap();
+    }
+
+    let mut diag = diag::error(&format!("rhai: {}", RE_POSITION.replace(message, "")));
+    if let Some(line) = pos.line() {
+        diag = diag.line(line - 1);
+    }
+    diag.into_error()
+}
+
+// same as dsl::mruby::value2str, string is quoted
+fn value2str(value: &Dynamic) -> String {
+    if value.is_unit() {
+        String::new()
+    } else if value.is_string() {
+        format!(r#""{}""#, value)
+    } else if value.is_array() {
+        let a = value.clone().cast::<Array>();
+        format!("[{}]", a.iter().map(value2str).collect::<Vec<String>>().join(", "))
+    } else {
+        value.to_string()
+    }
+}
@@ -1,51 +1,133 @@
-use std::{fs, env, ops};
-use std::{rc::Rc, cell::RefCell};
+use std::{fs, env, ops, path::Path};
+#[cfg(feature = "mruby")]
+use std::sync::Mutex;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::collections::HashMap;
 use std::io::{self, Error, ErrorKind};
 
 use regex::Regex;
-use mrusty::*;
 
 use crate::lib::repl;
 use crate::lib::cmd;
+use crate::lib::config;
+use crate::lib::diag;
+use crate::lib::dsl::{self, ScriptEngine};
 use crate::lib::term;
-use crate::lib::dsl;
 
-pub fn read(listdir: &str, alias_name: &str) -> io::Result<String> {
-    fs::read_to_string(format!("{}/{}.txt", listdir, alias_name))
+pub fn txt_path(listdir: &str, alias_name: &str) -> String {
+    format!("{}/{}.txt", listdir, alias_name)
 }
 
-enum Parsed<'a> {
+// alias body, and where it is defined
+pub struct Definition {
+    // <name>.txt or aliases.toml
+    pub file: String,
+    // 1-based line of body in file
+    pub line: usize,
+    pub body: String,
+}
+
+impl Definition {
+    // line in file, of 1-based line in body
+    pub fn file_line(&self, line: usize) -> usize {
+        self.line + line - 1
+    }
+}
+
+// <name>.txt takes precedence over aliases.toml
+pub fn read(listdir: &str, alias_name: &str) -> io::Result<Definition> {
+    let alias_txt = txt_path(listdir, alias_name);
+    if Path::new(&alias_txt).exists() {
+        return Ok(Definition { body: fs::read_to_string(&alias_txt)?, file: alias_txt, line: 1 });
+    }
+
+    if let Some(entry) = config::read()?.into_iter().find(|x| x.name == alias_name) {
+        return Ok(Definition { file: config::toml_path()?, line: entry.line, body: entry.body });
+    }
+
+    Err(Error::new(ErrorKind::NotFound, format!("{}: {} is not found in {}.txt or {}", term::ewrite("failed")?, alias_name, alias_name, config::ALIASES_TOML)))
+}
+
+pub enum Parsed<'a> {
     SetEnv(&'a str, &'a str),
     Pushd(&'a str),
     Popd(),
     Cmd(&'a str),
-    Mruby(&'a str),
+    // (<language>, <source>)
+    Script(&'a str, &'a str),
+    // (<line>, <@command>, <args>), not built-in, see parse_directive
+    Directive(&'a str, &'a str, &'a str),
 }
 
-pub fn run(alias_value: &str, args: &Vec<String>) -> io::Result<i32> {
-    // error, oh... ( help: the trait `std::marker::Sync` is not implemented for `std::rc::Rc<std::cell::RefCell<mrusty::mruby::Mruby>>` )
-    //lazy_static! {
-    //    static ref MRUBY: mrusty::MrubyType = mruby::mruby_new().unwrap();
-    //}
-    let mruby = dsl::mruby::mruby_new().unwrap();
-
-    let dir_stack = Rc::new(RefCell::new(Vec::new()));
-
-    parse_alias_value(alias_value, args, &mruby, |parsed| {
+pub fn run(def: &Definition, args: &Vec<String>) -> io::Result<i32> {
+    let script = Script::new(def, args);
+    let state = script.ctx.state.clone();
+    let result = parse_alias_value(def, args, &script, |parsed| {
         match parsed {
             Parsed::SetEnv(key, value) => setenv(key, value)?,
-            Parsed::Pushd(path) => pushd(&mut dir_stack.borrow_mut(), path)?,
-            Parsed::Popd() => popd(&mut dir_stack.borrow_mut())?,
+            Parsed::Pushd(path) => state.pushd(path)?,
+            Parsed::Popd() => state.popd()?,
             Parsed::Cmd(source) => cmd::command_spawn(source)?,
-            Parsed::Mruby(source) => { mruby_run(&mruby, source)?; },
+            Parsed::Script(lang, source) => script.run_block(lang, source)?,
+            Parsed::Directive(line, key, value) => script.run_directive(line, key, value)?,
         }
         Ok(())
-    })?;
+    });
+    if let Some(status) = state.take_exit_status() {
+        return Ok(status);
+    }
+    result?;
 
     Ok(0)
 }
 
-fn setenv(key: &str, value: &str) -> io::Result<()> {
+// engines of fenced blocks and <%= %>, created on first use.
+// aliases without them don't pay for an interpreter
+struct Script<'a> {
+    ctx: dsl::Context<'a>,
+    // by language, e.g. "ruby"
+    engines: RefCell<HashMap<String, Box<dyn ScriptEngine>>>,
+}
+
+impl<'a> Script<'a> {
+    fn new(def: &'a Definition, args: &'a Vec<String>) -> Script<'a> {
+        Script {
+            ctx: dsl::Context { file: &def.file, args, state: Rc::new(RunState::default()) },
+            engines: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn with_engine<T>(&self, lang: &str, f: impl FnOnce(&mut dyn ScriptEngine) -> io::Result<T>) -> io::Result<T> {
+        let mut engines = self.engines.borrow_mut();
+        if !engines.contains_key(lang) {
+            engines.insert(lang.to_owned(), dsl::new_engine(lang, &self.ctx)?);
+        }
+        f(engines.get_mut(lang).unwrap().as_mut())
+    }
+
+    fn run_block(&self, lang: &str, source: &str) -> io::Result<()> {
+        self.with_engine(lang, |engine| engine.eval(source))
+    }
+
+    // value of <%= %>, as cmd string
+    fn eval(&self, source: &str) -> io::Result<String> {
+        self.with_engine(dsl::NESTED_LANG, |engine| engine.eval_to_string(source))
+    }
+
+    fn run_directive(&self, line: &str, key: &str, value: &str) -> io::Result<()> {
+        // user library registers directives as mruby starts
+        #[cfg(feature = "mruby")]
+        self.with_engine("ruby", |_| Ok(()))?;
+
+        let (lang, args) = parse_directive(line, key, value)?;
+        self.with_engine(&lang, |engine| engine.call_directive(&key[1..], &args))
+    }
+}
+
+// @set, @pushd, @popd and exit, shared with script runtime
+
+pub fn setenv(key: &str, value: &str) -> io::Result<()> {
     lazy_static! {
         static ref RE_ENV: Regex = Regex::new(r"%(.+?)%").unwrap();
     }
@@ -61,40 +143,58 @@ fn setenv(key: &str, value: &str) -> io::Result<()> {
     env::set_var(key, value);
     Ok(())
 }
-fn pushd(stack: &mut Vec<String>, path: &str) -> io::Result<()> {
-    let prev = env::current_dir().unwrap().to_str().unwrap().to_owned();
-    if let Err(err) = env::set_current_dir(path) {
-        return Err(Error::new(ErrorKind::InvalidData, format!("{}: @pushd {}: {}", term::ewrite("failed")?, path, err)));
-    }
-    stack.push(prev);
-
-    Ok(())
+// state of one run, given to engines by dsl::Context
+#[derive(Default)]
+pub struct RunState {
+    // directories of @pushd
+    dir_stack: RefCell<Vec<String>>,
+    // status of exit(n) or abort in script
+    exit_status: Cell<Option<i32>>,
 }
 
-fn popd(stack: &mut Vec<String>) -> io::Result<()> {
-    let prev = stack.pop();
-    if None == prev {
-        return Err(Error::new(ErrorKind::InvalidData, format!("{}: @popd: directory stack empty", term::ewrite("failed")?)));
+impl RunState {
+    pub fn pushd(&self, path: &str) -> io::Result<()> {
+        let prev = env::current_dir().unwrap().to_str().unwrap().to_owned();
+        if let Err(err) = env::set_current_dir(path) {
+            return Err(diag::error(&format!("@pushd {}: {}", path, err)).into_error());
+        }
+        self.dir_stack.borrow_mut().push(prev);
+
+        Ok(())
     }
-    if let Err(err) = env::set_current_dir(prev.unwrap()) {
-        return Err(Error::new(ErrorKind::InvalidData, format!("{}: @popd: {}", term::ewrite("failed")?, err)));
+
+    pub fn popd(&self) -> io::Result<()> {
+        let prev = match self.dir_stack.borrow_mut().pop() {
+            Some(x) => x,
+            None => return Err(diag::error("@popd: directory stack empty")
+                .hint("@popd needs a preceding @pushd")
+                .into_error()),
+        };
+        if let Err(err) = env::set_current_dir(prev) {
+            return Err(diag::error(&format!("@popd: {}", err)).into_error());
+        }
+
+        Ok(())
     }
 
-    Ok(())
-}
+    // exit(n) or abort in script, terminating alias
+    pub fn exit(&self, status: i32) -> Error {
+        self.exit_status.set(Some(status));
+        Error::new(ErrorKind::Interrupted, "exit")
+    }
 
-fn mruby_run(mruby: &mrusty::MrubyType, source: &str) -> io::Result<mrusty::Value> {
-    let result = mruby.run(source);
-    if let Err(err) = result {
-        return Err(Error::new(ErrorKind::InvalidData, format!("{}: {}\n\n{}", term::ewrite("mruby failed")?, err, source)));
+    pub fn take_exit_status(&self) -> Option<i32> {
+        self.exit_status.take()
     }
-    Ok(result.unwrap())
 }
 
+// parse args($1, $2, etc)
+pub const ARGS: &str = r#"("\$[*+@]"|\$[0-9*+@#])"#;
+
 fn parse_alias_value(
-    alias_value: &str,
+    def: &Definition,
     args: &Vec<String>,
-    mruby: &mrusty::MrubyType,
+    script: &Script,
     frun: impl Fn(Parsed) -> io::Result<()>
 )
     -> io::Result<()>
@@ -102,17 +202,12 @@ fn parse_alias_value(
     const NESTED_CMD: &'static str = r"(?ms)\$\((.*?)\)";
     const NESTED_MRUBY: &'static str = r"(?ms)<%=(.*?)%>";
     lazy_static! {
-        // parse args($1, $2, etc)
-        static ref RE_ARGS: Regex = Regex::new(r#"("\$[*+@]"|\$[0-9*+@#])"#).unwrap();
+        static ref RE_ARGS: Regex = Regex::new(ARGS).unwrap();
         // parse nested $( ... ) or <%= ... %>
         static ref RE_NESTED_CMD: Regex = Regex::new(NESTED_CMD).unwrap();
         static ref RE_NESTED_MRUBY: Regex = Regex::new(NESTED_MRUBY).unwrap();
         static ref RE_NESTED: Regex = Regex::new(&format!("{}|{}", NESTED_CMD, NESTED_MRUBY)).unwrap();
     }
-    let alias_value = repl::replace_all_func(&RE_ARGS, alias_value, |caps| parse_arg(caps.get(0).unwrap().as_str(), args))?;
-
-    // replace multiple line for cmd
-    let alias_value = alias_value.replace("^\n", "");
 
     let run_nested = |caps: &regex::Captures| {
         let s = caps.get(0).unwrap().as_str();
@@ -122,25 +217,36 @@ fn parse_alias_value(
             let source = cap.get(1).unwrap().as_str();
             cmd::command_output(source)
         } else {
-            // nested mruby
+            // nested script
             let cap = RE_NESTED_MRUBY.captures(&s).unwrap();
             let source = cap.get(1).unwrap().as_str();
-            let value = mruby_run(mruby, source)?;
-            dsl::mruby::value2str(mruby, value)
+            script.eval(source)
         }
     };
+    let replace_args = |source: &str| {
+        repl::replace_all_func(&RE_ARGS, source, |caps| parse_arg(caps.get(0).unwrap().as_str(), args))
+    };
 
-    split_source_func(&alias_value, |source| {
+    split_source_func(&def.body, |source| {
         match source {
-            Source::Cmd(cmd_source) => {
-                validate_nested(cmd_source)?;
-                let cmd_source = repl::replace_all_func_nested(&RE_NESTED, cmd_source, run_nested)?;
-                frun(parse_cmd_type(&cmd_source)?)?;
+            Source::Cmd(chunk) => {
+                let run_cmd = || {
+                    // replace multiple line for cmd
+                    let cmd_source = replace_args(&chunk.source.replace("^\n", ""))?;
+                    validate_nested(&cmd_source)?;
+                    let cmd_source = repl::replace_all_func_nested(&RE_NESTED, &cmd_source, run_nested)?;
+                    frun(parse_cmd_type(&cmd_source)?)
+                };
+                run_cmd().map_err(|err| diag::locate(err, &def.file, def.file_line(chunk.line), chunk.source))?;
             },
-            Source::Mruby(mruby_source) => {
-                validate_nested(mruby_source)?;
-                let mruby_source = repl::replace_all_func_nested(&RE_NESTED, mruby_source, run_nested)?;
-                frun(Parsed::Mruby(&mruby_source))?;
+            Source::Script(lang, chunk) => {
+                let run_script = || {
+                    let script_source = replace_args(chunk.source)?;
+                    validate_nested(&script_source)?;
+                    let script_source = repl::replace_all_func_nested(&RE_NESTED, &script_source, run_nested)?;
+                    frun(Parsed::Script(lang, &script_source))
+                };
+                run_script().map_err(|err| diag::locate(err, &def.file, def.file_line(chunk.line), chunk.source))?;
             },
         }
         Ok(())
@@ -151,67 +257,100 @@ fn parse_alias_value(
 
 // ---
 
-enum Source<'a> {
-    Cmd(&'a str),
-    Mruby(&'a str),
+pub enum Source<'a> {
+    Cmd(Chunk<'a>),
+    // (<language>, <chunk>)
+    Script(&'a str, Chunk<'a>),
+}
+
+pub struct Chunk<'a> {
+    // 1-based line number of source in alias file
+    pub line: usize,
+    pub source: &'a str,
 }
 
-fn split_source_func(
+pub fn split_source_func(
     alias_value: &str,
     fsource: impl Fn(Source) -> io::Result<()>
 )
     -> io::Result<()>
 {
     lazy_static! {
-        static ref RE_MRUBY_RANGE: Regex = Regex::new(r"(?ms)^\s*```ruby\s*$\n(.+?)\n^\s*```\s*$").unwrap();
-
-        static ref RE_CMD_COMMENT: Regex = Regex::new(r"^#").unwrap();
+        // ```ruby, ```rhai or ```lua, whether built in or not
+        static ref RE_SCRIPT_RANGE: Regex = Regex::new(&format!(r"(?ms)^\s*```({})\s*$\n(.+?)\n^\s*```\s*$",
+            dsl::LANGS.iter().map(|x| x.0).collect::<Vec<&str>>().join("|"))).unwrap();
     }
 
     let mut cur = 0;
-    for caps in RE_MRUBY_RANGE.captures_iter(alias_value) {
+    for caps in RE_SCRIPT_RANGE.captures_iter(alias_value) {
         let m = caps.get(0).unwrap();
         let start = m.start();
         if start > cur {
             // cmd source
-            for cmd_source in alias_value[cur..start].trim()
-                .split('\n')
-                .map(|x| x.trim())
-                .filter(|x| x.len() > 0)
-                .filter(|x| !RE_CMD_COMMENT.is_match(x))
-            {
-                fsource(Source::Cmd(cmd_source))?;
-            }
+            split_cmd_func(alias_value, cur..start, &fsource)?;
         }
 
-        // mruby source
-        let m1 = caps.get(1).unwrap();
-        let mruby_source = &alias_value[m1.start()..m1.end()].trim();
-        fsource(Source::Mruby(mruby_source))?;
+        // script source
+        let lang = caps.get(1).unwrap().as_str();
+        let m2 = caps.get(2).unwrap();
+        let raw = &alias_value[m2.start()..m2.end()];
+        let offset = m2.start() + (raw.len() - raw.trim_start().len());
+        fsource(Source::Script(lang, Chunk { line: line_of(alias_value, offset), source: raw.trim() }))?;
 
         cur = m.end();
     }
 
     // remain cmd source
     if cur < alias_value.len() {
-        for cmd_source in alias_value[cur..].trim()
-            .split('\n')
-            .map(|x| x.trim())
-            .filter(|x| x.len() > 0)
-            .filter(|x| !RE_CMD_COMMENT.is_match(x))
-        {
-            fsource(Source::Cmd(cmd_source))?;
+        split_cmd_func(alias_value, cur..alias_value.len(), &fsource)?;
+    }
+    Ok(())
+}
+
+fn split_cmd_func(
+    alias_value: &str,
+    range: ops::Range<usize>,
+    fsource: &impl Fn(Source) -> io::Result<()>
+)
+    -> io::Result<()>
+{
+    lazy_static! {
+        static ref RE_CMD_COMMENT: Regex = Regex::new(r"^#").unwrap();
+    }
+
+    let mut offset = range.start;
+    // start of multiple line cmd, continued by '^'
+    let mut continued: Option<usize> = None;
+    for line in alias_value[range.clone()].split('\n') {
+        let line_start = offset;
+        offset += line.len() + 1;
+
+        let start = continued.take().unwrap_or(line_start);
+        if line.ends_with('^') && offset < range.end {
+            continued = Some(start);
+            continue;
         }
+
+        let raw = &alias_value[start..line_start + line.len()];
+        let source = raw.trim();
+        if source.is_empty() || RE_CMD_COMMENT.is_match(source) {
+            continue;
+        }
+        let line = line_of(alias_value, start + (raw.len() - raw.trim_start().len()));
+        fsource(Source::Cmd(Chunk { line, source }))?;
     }
     Ok(())
 }
 
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
 // ---
 
-fn parse_cmd_type(alias_value: &str) -> io::Result<Parsed> {
+pub fn parse_cmd_type(alias_value: &str) -> io::Result<Parsed<'_>> {
     lazy_static! {
         static ref RE_AT: Regex = Regex::new(r"^@").unwrap();
-        static ref RE_AT_KEY: Regex = Regex::new(r"^(@[^\s]+)").unwrap();
         static ref RE_AT_KEY_VALUE: Regex = Regex::new(r"^(@[^\s]+)(.*)").unwrap();
 
         static ref RE_SET: Regex = Regex::new(r"^@set").unwrap();
@@ -224,8 +363,9 @@ fn parse_cmd_type(alias_value: &str) -> io::Result<Parsed> {
     if RE_AT.is_match(alias_value) {
         let caps = RE_AT_KEY_VALUE.captures(alias_value);
         if caps.is_none() {
-            let (s1, s2, s3) = repl::partition_re(&RE_AT, alias_value).unwrap();
-            return Err(Error::new(ErrorKind::InvalidData, format!("{}: illegal @command format\n\n{}{}{}", term::ewrite("failed")?, s1, term::ewrite(s2)?, s3)));
+            return Err(diag::error("illegal @command format")
+                .label(alias_value, RE_AT.find(alias_value).unwrap().range())
+                .into_error());
         }
         let caps = caps.unwrap();
         let key = caps.get(1).unwrap().as_str();
@@ -235,8 +375,10 @@ fn parse_cmd_type(alias_value: &str) -> io::Result<Parsed> {
             "@set" => {
                 let caps = RE_SET_KEY_VALUE.captures(value);
                 if caps.is_none() {
-                    let (s1, s2, s3) = repl::partition_re(&RE_SET, alias_value).unwrap();
-                    return Err(Error::new(ErrorKind::InvalidData, format!("{}: illegal @set format\n\n{}{}{}", term::ewrite("failed")?, s1, term::ewrite(s2)?, s3)));
+                    return Err(diag::error("illegal @set format")
+                        .label(alias_value, RE_SET.find(alias_value).unwrap().range())
+                        .hint("usage: @set <key>=<value>")
+                        .into_error());
                 }
 
                 let caps = caps.unwrap();
@@ -247,31 +389,119 @@ fn parse_cmd_type(alias_value: &str) -> io::Result<Parsed> {
             },
             "@pushd" => {
                 if value.len() == 0 {
-                    let (s1, s2, s3) = repl::partition_re(&RE_PUSHD, alias_value).unwrap();
-                    return Err(Error::new(ErrorKind::InvalidData, format!("{}: @pushd path is none\n\n{}{}{}", term::ewrite("failed")?, s1, term::ewrite(s2)?, s3)));
+                    return Err(diag::error("@pushd path is none")
+                        .label(alias_value, RE_PUSHD.find(alias_value).unwrap().range())
+                        .hint("usage: @pushd <path>")
+                        .into_error());
                 }
 
                 return Ok(Parsed::Pushd(value));
             },
             "@popd" => {
                 if value.len() > 0 {
-                    let (s1, s2, s3) = repl::partition_re(&RE_POPD, alias_value).unwrap();
-                    return Err(Error::new(ErrorKind::InvalidData, format!("{}: @popd unknown args\n\n{}{}{}", term::ewrite("failed")?, s1, term::ewrite(s2)?, s3)));
+                    return Err(diag::error("@popd unknown args")
+                        .label(alias_value, RE_POPD.find(alias_value).unwrap().end()..alias_value.len())
+                        .hint("usage: @popd")
+                        .into_error());
                 }
 
                 return Ok(Parsed::Popd());
             },
-            _ => {
-                let (s1, s2, s3) = repl::partition_re(&RE_AT_KEY, alias_value).unwrap();
-                return Err(Error::new(ErrorKind::InvalidData, format!("{}: {} is unknown @command\n\n{}{}{}", term::ewrite("failed")?, key, s1, term::ewrite(s2)?, s3)));
-            }
+            _ => Ok(Parsed::Directive(alias_value, key, value)),
         }
     } else {
         Ok(Parsed::Cmd(alias_value))
     }
 }
 
-fn validate_nested(alias_value: &str) -> io::Result<()> {
+// @command defined by script, e.g. Alias.directive in mruby
+#[cfg(feature = "mruby")]
+pub struct Directive {
+    // language of engine calling it
+    pub lang: String,
+    // after @command, e.g. "<path>"
+    pub usage: String,
+    pub min_args: usize,
+    pub max_args: Option<usize>,
+}
+
+#[cfg(feature = "mruby")]
+lazy_static! {
+    // by @command
+    static ref DIRECTIVES: Mutex<HashMap<String, Directive>> = Mutex::new(HashMap::new());
+}
+
+// built-in @commands can't be redefined
+#[cfg(feature = "mruby")]
+pub fn register_directive(name: &str, directive: Directive) -> io::Result<()> {
+    lazy_static! {
+        static ref RE_NAME: Regex = Regex::new(r"^[^\s@]+$").unwrap();
+    }
+
+    let key = format!("@{}", name);
+    if !RE_NAME.is_match(name) {
+        return Err(diag::error(&format!("illegal @command name: {}", name)).into_error());
+    }
+    if ["@set", "@pushd", "@popd"].contains(&key.as_str()) {
+        return Err(diag::error(&format!("{} is built-in @command", key)).into_error());
+    }
+    DIRECTIVES.lock().unwrap().insert(key, directive);
+    Ok(())
+}
+
+// (<language>, <args>) of Parsed::Directive, args are split like cmd
+#[cfg(feature = "mruby")]
+pub fn parse_directive(alias_value: &str, key: &str, value: &str) -> io::Result<(String, Vec<String>)> {
+    lazy_static! {
+        static ref RE_AT_KEY: Regex = Regex::new(r"^(@[^\s]+)").unwrap();
+    }
+
+    let directives = DIRECTIVES.lock().unwrap();
+    let directive = match directives.get(key) {
+        Some(x) => x,
+        None => return Err(unknown_directive(alias_value, key, directives.keys().map(|x| x.as_str()).collect())),
+    };
+
+    let args = cmd::split_args(value);
+    let usage = format!("usage: {} {}", key, directive.usage);
+    if args.len() < directive.min_args {
+        return Err(diag::error(&format!("{} args are too few", key))
+            .label(alias_value, RE_AT_KEY.find(alias_value).unwrap().range())
+            .hint(usage.trim_end())
+            .into_error());
+    }
+    if directive.max_args.map(|max| args.len() > max).unwrap_or(false) {
+        return Err(diag::error(&format!("{} unknown args", key))
+            .label(alias_value, RE_AT_KEY.find(alias_value).unwrap().end()..alias_value.len())
+            .hint(usage.trim_end())
+            .into_error());
+    }
+
+    Ok((directive.lang.clone(), args))
+}
+
+// only built-ins, @commands are defined by mruby
+#[cfg(not(feature = "mruby"))]
+pub fn parse_directive(alias_value: &str, key: &str, _value: &str) -> io::Result<(String, Vec<String>)> {
+    Err(unknown_directive(alias_value, key, Vec::new()))
+}
+
+fn unknown_directive(alias_value: &str, key: &str, mut keys: Vec<&str>) -> io::Error {
+    lazy_static! {
+        static ref RE_AT_KEY: Regex = Regex::new(r"^(@[^\s]+)").unwrap();
+    }
+
+    keys.sort();
+    let mut available = vec!["@set", "@pushd", "@popd"];
+    available.extend(keys);
+    let last = available.pop().unwrap();
+    diag::error(&format!("{} is unknown @command", key))
+        .label(alias_value, RE_AT_KEY.find(alias_value).unwrap().range())
+        .hint(&format!("available @commands are {} and {}", available.join(", "), last))
+        .into_error()
+}
+
+pub fn validate_nested(alias_value: &str) -> io::Result<()> {
     lazy_static! {
         static ref RE_NESTED: Regex = Regex::new(r"(?ms)(\$\(|%\w\(|\(|<%=|%>|\))").unwrap();
     }
@@ -340,24 +570,16 @@ fn validate_nested(alias_value: &str) -> io::Result<()> {
         erred.append(&mut nested_mruby);
         erred.sort_by(|a, b| a.start.cmp(&b.start));
 
-        // Colorize error location
-        let mut s = String::new();
-        let mut idx = 0;
-        for rng in erred {
-            s.push_str(&alias_value[idx..rng.start]);
-            s.push_str(&term::ewrite(&alias_value[rng.start..rng.end])?);
-            idx = rng.end;
-        }
-        if idx < alias_value.len() {
-            s.push_str(&alias_value[idx..]);
-        }
-        return Err(Error::new(ErrorKind::InvalidData, format!("{}: nested command syntax error\n\n{}", term::ewrite("failed")?, s)));
+        return Err(diag::error("nested command syntax error")
+            .labels(alias_value, erred)
+            .hint("$( ... ) and <%= ... %> must be balanced")
+            .into_error());
     }
 
     Ok(())
 }
 
-fn parse_arg(arg: &str, args: &Vec<String>) -> io::Result<String> {
+pub fn parse_arg(arg: &str, args: &[String]) -> io::Result<String> {
     let f = |s: &str| {
         if let Some(_) = s.find(char::is_whitespace) {
             format!(r#""{}""#, s)
@@ -378,9 +600,9 @@ fn parse_arg(arg: &str, args: &Vec<String>) -> io::Result<String> {
         "$8" => Ok(if args.len() > 8 { args[8].clone() } else { "".to_owned() }),
         "$9" => Ok(if args.len() > 9 { args[9].clone() } else { "".to_owned() }),
         "$#" => Ok(format!("{}", args.len() - 1)),
-        "$*" => Err(Error::new(ErrorKind::InvalidData, format!("{}: $* is not supported", term::ewrite("failed")?))),
-        "$@" => Err(Error::new(ErrorKind::InvalidData, format!("{}: $@ is not supported, maybe \"$@\" ?", term::ewrite("failed")?))),
-        "\"$*\"" => Err(Error::new(ErrorKind::InvalidData, format!("{}: \"$*\" is not supported, maybe \"$+\" ?", term::ewrite("failed")?))),
+        "$*" => Err(diag::error("$* is not supported").hint("use \"$@\" or \"$+\"").into_error()),
+        "$@" => Err(diag::error("$@ is not supported").hint("maybe \"$@\" ?").into_error()),
+        "\"$*\"" => Err(diag::error("\"$*\" is not supported").hint("maybe \"$+\" ?").into_error()),
         "\"$+\"" => Ok(format!(r#""{}""#, str_join(args.iter().skip(1).map(|x| x.to_string()), " "))),
         "\"$@\"" => Ok(str_join(args.iter().skip(1).map(|x| f(x)), " ")),
         _ => Ok(arg.to_string()),
@@ -0,0 +1,86 @@
+use std::io;
+
+use regex::Regex;
+
+use crate::lib::dsl;
+use crate::lib::term::{self, Color};
+
+const COMMENT: Color = Color::Blue;
+const DIRECTIVE: Color = Color::Cyan;
+const ARG: Color = Color::Green;
+const NESTED_CMD: Color = Color::Yellow;
+const SCRIPT: Color = Color::Magenta;
+
+// colorize alias value for terminal
+pub fn highlight(alias_value: &str) -> io::Result<String> {
+    lazy_static! {
+        // ```ruby, ```rhai or ```lua, whether built in or not
+        static ref RE_SCRIPT_START: Regex = Regex::new(&format!(r"^\s*```({})\s*$",
+            dsl::LANGS.iter().map(|x| x.0).collect::<Vec<&str>>().join("|"))).unwrap();
+        static ref RE_SCRIPT_END: Regex = Regex::new(r"^\s*```\s*$").unwrap();
+        static ref RE_COMMENT: Regex = Regex::new(r"^\s*#").unwrap();
+        static ref RE_DIRECTIVE: Regex = Regex::new(r"^(\s*)(@[^\s]+)(.*)$").unwrap();
+    }
+
+    let mut s = String::new();
+    let mut in_script = false;
+    for (i, line) in alias_value.split('\n').enumerate() {
+        if i > 0 {
+            s.push('\n');
+        }
+
+        if in_script {
+            in_script = !RE_SCRIPT_END.is_match(line);
+            s.push_str(&term::colorwrite(line, SCRIPT)?);
+        } else if RE_SCRIPT_START.is_match(line) {
+            in_script = true;
+            s.push_str(&term::colorwrite(line, SCRIPT)?);
+        } else if RE_COMMENT.is_match(line) {
+            s.push_str(&term::colorwrite(line, COMMENT)?);
+        } else if let Some(caps) = RE_DIRECTIVE.captures(line) {
+            s.push_str(&caps[1]);
+            s.push_str(&term::colorwrite(&caps[2], DIRECTIVE)?);
+            s.push_str(&highlight_cmd(&caps[3])?);
+        } else {
+            s.push_str(&highlight_cmd(line)?);
+        }
+    }
+    Ok(s)
+}
+
+fn highlight_cmd(line: &str) -> io::Result<String> {
+    lazy_static! {
+        static ref RE_TOKEN: Regex = Regex::new(r#"(<%=.*?%>|"\$[*+@]"|\$[0-9*+@#]|\$\(|\(|\))"#).unwrap();
+    }
+
+    let mut s = String::new();
+    // open brackets, true if $(
+    let mut brackets: Vec<bool> = Vec::new();
+    let mut idx = 0;
+    for m in RE_TOKEN.find_iter(line) {
+        s.push_str(&line[idx..m.start()]);
+        let token = m.as_str();
+        let color = match token {
+            "$(" => {
+                brackets.push(true);
+                Some(NESTED_CMD)
+            },
+            "(" => {
+                brackets.push(false);
+                None
+            },
+            ")" => {
+                if brackets.pop().unwrap_or(false) { Some(NESTED_CMD) } else { None }
+            },
+            _ if token.starts_with("<%=") => Some(SCRIPT),
+            _ => Some(ARG),
+        };
+        match color {
+            Some(color) => s.push_str(&term::colorwrite(token, color)?),
+            None => s.push_str(token),
+        }
+        idx = m.end();
+    }
+    s.push_str(&line[idx..]);
+    Ok(s)
+}
@@ -0,0 +1,214 @@
+use std::{cell::{Cell, RefCell}, collections::{BTreeSet, HashMap}};
+use std::io;
+
+use regex::Regex;
+
+use crate::lib::path;
+use crate::lib::exec::{self, Definition, Source, Parsed};
+use crate::lib::diag::{self, Diagnostic};
+use crate::lib::dsl::{self, ScriptEngine};
+
+pub struct Lint {
+    pub alias_name: String,
+    // kind of check, e.g. "unbalanced-pushd"
+    pub code: &'static str,
+    pub diag: Diagnostic,
+}
+
+// check alias without executing
+pub fn lint(alias_name: &str, def: &Definition) -> io::Result<Vec<Lint>> {
+    lazy_static! {
+        static ref RE_ARGS: Regex = Regex::new(exec::ARGS).unwrap();
+        // comment declares args, e.g. "# $1: hello, $2: world"
+        static ref RE_DECLARED: Regex = Regex::new(r"(?m)^\s*#.*$").unwrap();
+        static ref RE_DECLARED_ARG: Regex = Regex::new(r"\$([1-9]):").unwrap();
+        static ref RE_NUMBERED_ARG: Regex = Regex::new(r"\$([1-9])").unwrap();
+        static ref RE_NESTED: Regex = Regex::new(r"(?ms)\$\(.*?\)|<%=.*?%>").unwrap();
+        static ref RE_NESTED_SCRIPT: Regex = Regex::new(r"(?ms)<%=(.*?)%>").unwrap();
+        // runtime exit() at top level, e.g. "exit", "exit 1" or "exit(1);"
+        static ref RE_EXIT: Regex = Regex::new(r"^exit(\s*\(\s*-?\d*\s*\)|\s+-?\d+)?\s*;?$").unwrap();
+    }
+
+    let lints = RefCell::new(Vec::new());
+    let push = |code: &'static str, diag: Diagnostic| {
+        lints.borrow_mut().push(Lint { alias_name: alias_name.to_owned(), code, diag });
+    };
+
+    let declared: BTreeSet<String> = RE_DECLARED.find_iter(&def.body)
+        .flat_map(|m| RE_DECLARED_ARG.captures_iter(m.as_str()).map(|caps| caps[1].to_owned()).collect::<Vec<_>>())
+        .collect();
+
+    // quote: cmd chunk, where args are split by spaces
+    let check_args = |chunk: &exec::Chunk, source: &str, quote: bool| {
+        // @set and @pushd take the rest of line as is
+        let quote = quote && !source.trim_start().starts_with('@');
+        for m in RE_ARGS.find_iter(source) {
+            if let Err(err) = exec::parse_arg(m.as_str(), &[alias_name.to_owned()]) {
+                push("unsupported-arg", diag::error(&err.to_string())
+                    .label(source, m.range())
+                    .locate(&def.file, def.file_line(chunk.line), chunk.source));
+            }
+        }
+        for caps in RE_NUMBERED_ARG.captures_iter(source) {
+            let m = caps.get(0).unwrap();
+            if !declared.is_empty() && !declared.contains(&caps[1]) {
+                push("undeclared-arg", diag::warning(&format!("{} is not declared", m.as_str()))
+                    .label(source, m.range())
+                    .hint(&format!("declare it in a comment, e.g. `# {}: <description>`", m.as_str()))
+                    .locate(&def.file, def.file_line(chunk.line), chunk.source));
+            }
+            // not in <%= %>, $1 of ruby
+            let in_script = RE_NESTED_SCRIPT.find_iter(source).any(|x| x.start() <= m.start() && m.end() <= x.end());
+            let in_quotes = source[..m.start()].matches('"').count() % 2 == 1;
+            if quote && !in_script && !in_quotes {
+                push("unquoted-arg", diag::warning(&format!("unquoted {} is split if it contains spaces", m.as_str()))
+                    .label(source, m.range())
+                    .hint(&format!("quote it, \"{}\"", m.as_str()))
+                    .locate(&def.file, def.file_line(chunk.line), chunk.source));
+            }
+        }
+    };
+
+    // compile only, don't run. checker is created on first block of its language
+    let ctx_args = vec![alias_name.to_owned()];
+    let ctx = dsl::Context { file: &def.file, args: &ctx_args, state: Default::default() };
+    let engines: RefCell<HashMap<String, Box<dyn ScriptEngine>>> = RefCell::new(HashMap::new());
+    let check_script = |lang: &str, chunk: &exec::Chunk, source: &str| {
+        let mut engines = engines.borrow_mut();
+        if !engines.contains_key(lang) {
+            match dsl::new_checker(lang, &ctx) {
+                Ok(engine) => { engines.insert(lang.to_owned(), engine); },
+                Err(err) => {
+                    push("engine", diag::from_error(err).locate(&def.file, def.file_line(chunk.line), chunk.source));
+                    return;
+                },
+            }
+        }
+        let source = RE_NESTED.replace_all(source, "nil");
+        if let Err(err) = engines.get_mut(lang).unwrap().check(&source) {
+            push(syntax_code(lang), diag::from_error(err).locate(&def.file, def.file_line(chunk.line), chunk.source));
+        }
+    };
+
+    // @pushd lines not popped yet
+    let pushed: RefCell<Vec<(usize, String)>> = RefCell::new(Vec::new());
+    // line of block ending with exit(), lines after it are reported once
+    let exited: Cell<Option<usize>> = Cell::new(None);
+    let reported = Cell::new(false);
+    let library = library_directives();
+
+    exec::split_source_func(&def.body, |source| {
+        if let (Some(line), false) = (exited.get(), reported.get()) {
+            let chunk = match source {
+                Source::Cmd(ref chunk) | Source::Script(_, ref chunk) => chunk,
+            };
+            if chunk.source.lines().any(|x| !x.trim().is_empty() && !x.trim_start().starts_with('#')) {
+                push("unreachable", diag::warning("unreachable, alias exits before it")
+                    .hint(&format!("exit() at line {}", def.file_line(line)))
+                    .locate(&def.file, def.file_line(chunk.line), chunk.source));
+                reported.set(true);
+            }
+        }
+        match source {
+            Source::Cmd(chunk) => {
+                let cmd_source = chunk.source.replace("^\n", "");
+                check_args(&chunk, &cmd_source, true);
+
+                if let Err(err) = exec::validate_nested(&cmd_source) {
+                    push("nested-syntax", diag::from_error(err).locate(&def.file, def.file_line(chunk.line), chunk.source));
+                }
+                for caps in RE_NESTED_SCRIPT.captures_iter(&cmd_source) {
+                    check_script(dsl::NESTED_LANG, &chunk, &caps[1]);
+                }
+                match exec::parse_cmd_type(&cmd_source) {
+                    Ok(Parsed::Pushd(_)) => pushed.borrow_mut().push((chunk.line, chunk.source.to_owned())),
+                    Ok(Parsed::Popd()) => {
+                        if pushed.borrow_mut().pop().is_none() {
+                            push("unbalanced-pushd", diag::error("@popd: directory stack empty")
+                                .hint("@popd needs a preceding @pushd")
+                                .locate(&def.file, def.file_line(chunk.line), chunk.source));
+                        }
+                    },
+                    // usage of one in user library is unknown, it isn't run
+                    Ok(Parsed::Directive(_, key, _)) if library.contains(&key[1..]) => (),
+                    Ok(Parsed::Directive(line, key, value)) => {
+                        if let Err(err) = exec::parse_directive(line, key, value) {
+                            push("directive", diag::from_error(err).locate(&def.file, def.file_line(chunk.line), chunk.source));
+                        }
+                    },
+                    Ok(_) => (),
+                    Err(err) => push("directive", diag::from_error(err).locate(&def.file, def.file_line(chunk.line), chunk.source)),
+                }
+            },
+            Source::Script(lang, chunk) => {
+                check_args(&chunk, chunk.source, false);
+
+                if let Err(err) = exec::validate_nested(chunk.source) {
+                    push("nested-syntax", diag::from_error(err).locate(&def.file, def.file_line(chunk.line), chunk.source));
+                }
+                check_script(lang, &chunk, chunk.source);
+
+                let last = chunk.source.lines().map(|x| x.trim()).rfind(|x| !x.is_empty() && !x.starts_with('#') && !x.starts_with("--"));
+                if exited.get().is_none() && last.map(|x| RE_EXIT.is_match(x)).unwrap_or(false) {
+                    exited.set(Some(chunk.line));
+                }
+            },
+        }
+        Ok(())
+    })?;
+
+    for (line, source) in pushed.into_inner() {
+        push("unbalanced-pushd", diag::warning("@pushd without matching @popd")
+            .locate(&def.file, def.file_line(line), &source));
+    }
+
+    let list_dir = path::alias_list_dir();
+    let found = path::which(alias_name);
+    if let Some(i) = found.iter().position(|x| x.parent().map(|dir| path::same_path(dir, &list_dir)).unwrap_or(false)) {
+        for (j, other) in found.iter().enumerate().filter(|&(j, _)| j != i) {
+            let message = if j < i {
+                format!("alias is shadowed by {}", other.display())
+            } else {
+                format!("alias shadows {}", other.display())
+            };
+            push("shadowed", diag::warning(&message).in_file(&def.file));
+        }
+    }
+
+    Ok(lints.into_inner())
+}
+
+// @commands defined by Alias.directive in <cfg_path>/lib/*.rb, found by text
+#[cfg(feature = "mruby")]
+fn library_directives() -> BTreeSet<String> {
+    lazy_static! {
+        static ref RE_DIRECTIVE: Regex = Regex::new(r#"Alias\.directive\(?\s*[:"']([^\s,"')]+)"#).unwrap();
+    }
+
+    let mut names = BTreeSet::new();
+    let lib_dir = match path::cfg_lib_path() {
+        Ok(x) => x,
+        Err(_) => return names,
+    };
+    for entry in std::fs::read_dir(&lib_dir).into_iter().flatten().flatten() {
+        if entry.path().extension().map(|x| x == "rb").unwrap_or(false) {
+            let source = std::fs::read_to_string(entry.path()).unwrap_or_default();
+            names.extend(RE_DIRECTIVE.captures_iter(&source).map(|caps| caps[1].to_owned()));
+        }
+    }
+    names
+}
+
+#[cfg(not(feature = "mruby"))]
+fn library_directives() -> BTreeSet<String> {
+    BTreeSet::new()
+}
+
+fn syntax_code(lang: &str) -> &'static str {
+    match lang {
+        "ruby" => "ruby-syntax",
+        "rhai" => "rhai-syntax",
+        "lua" => "lua-syntax",
+        _ => "expr-syntax",
+    }
+}
@@ -0,0 +1,131 @@
+use std::{env, fs, process};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::io::{self, Error, ErrorKind};
+
+use serde::{Serialize, Deserialize};
+
+use crate::lib::path;
+use crate::lib::config;
+use crate::lib::term;
+
+// <alias_name>.meta.toml
+//
+//   description = "grep in git files"
+//   tags = ["git", "search"]
+//   owner = "wordijp"
+//   shell = "cmd"
+//   created = "2020-03-01T12:34:56Z"
+//   updated = "2020-03-02T08:00:00Z"
+//   runs = 42
+//   last_run = "2020-03-02T09:00:00Z"
+//
+// description, tags, owner and shell can also be written in aliases.toml
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Meta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    // shell backend, e.g. "cmd"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    #[serde(default)]
+    pub runs: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<String>,
+}
+
+pub fn meta_path(listdir: &str, alias_name: &str) -> String {
+    format!("{}/{}.meta.toml", listdir, alias_name)
+}
+
+// sidecar file, and aliases.toml for missing fields
+pub fn read(alias_name: &str) -> io::Result<Meta> {
+    let mut meta = read_sidecar(alias_name)?;
+
+    if let Some(entry) = config::read()?.into_iter().find(|x| x.name == alias_name) {
+        meta.description = meta.description.or(entry.meta.description);
+        if meta.tags.is_empty() {
+            meta.tags = entry.meta.tags;
+        }
+        meta.owner = meta.owner.or(entry.meta.owner);
+        meta.shell = meta.shell.or(entry.meta.shell);
+    }
+    Ok(meta)
+}
+
+// sidecar file only, to be written back. fields of aliases.toml are not copied into it
+pub fn read_sidecar(alias_name: &str) -> io::Result<Meta> {
+    let meta_toml = meta_path(&path::cfg_list_path()?, alias_name);
+    if !Path::new(&meta_toml).exists() {
+        return Ok(Meta::default());
+    }
+
+    let text = fs::read_to_string(&meta_toml)?;
+    match toml::from_str(&text) {
+        Ok(meta) => Ok(meta),
+        Err(err) => Err(Error::new(ErrorKind::InvalidData, format!("{}: {}: {}", term::ewrite("failed")?, meta_toml, err))),
+    }
+}
+
+// through temp file, aliases may run in parallel, e.g. git hooks
+pub fn write(alias_name: &str, meta: &Meta) -> io::Result<()> {
+    let text = match toml::to_string(meta) {
+        Ok(text) => text,
+        Err(err) => return Err(Error::new(ErrorKind::InvalidData, format!("{}: {}", term::ewrite("failed")?, err))),
+    };
+    let meta_toml = meta_path(&path::cfg_list_path()?, alias_name);
+    let tmp = format!("{}.{}.tmp", meta_toml, process::id());
+    let result = fs::write(&tmp, text).and_then(|_| fs::rename(&tmp, &meta_toml));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+// alias body was written
+pub fn touch(alias_name: &str) -> io::Result<()> {
+    let mut meta = read_sidecar(alias_name)?;
+    let now = now();
+    if meta.created.is_none() {
+        meta.created = Some(now.clone());
+        meta.owner = meta.owner.or(env::var("USERNAME").ok());
+    }
+    meta.updated = Some(now);
+    write(alias_name, &meta)
+}
+
+// alias was executed, concurrent runs may be counted once
+pub fn record_run(alias_name: &str) -> io::Result<()> {
+    let mut meta = read_sidecar(alias_name)?;
+    meta.runs += 1;
+    meta.last_run = Some(now());
+    write(alias_name, &meta)
+}
+
+// UTC, e.g. "2020-03-01T12:34:56Z"
+pub fn now() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+
+    // days since 1970-01-01, to civil date
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3600, rem / 60 % 60, rem % 60)
+}
@@ -1,8 +1,19 @@
 pub mod path;
 pub mod alias;
+pub mod bundle;
+pub mod config;
 pub mod exec;
 pub mod encode;
 pub mod repl;
 pub mod cmd;
+pub mod convert;
 pub mod term;
+pub mod diag;
+pub mod doctor;
+pub mod lint;
+pub mod meta;
+pub mod script;
+pub mod search;
+pub mod spec;
+pub mod highlight;
 pub mod dsl;
@@ -14,8 +14,9 @@ lazy_static! {
     static ref RE_EXE: Regex = Regex::new(r"\.[eE][xX][eE]$").unwrap();
 }
 
+// dangling links too
 pub fn is_exe(path: &path::PathBuf) -> bool {
-    RE_EXE.is_match(path.to_str().unwrap()) && fs::metadata(path).unwrap().is_file()
+    RE_EXE.is_match(path.to_str().unwrap()) && fs::symlink_metadata(path).map(|x| !x.is_dir()).unwrap_or(false)
 }
 
 
@@ -27,6 +28,52 @@ pub fn is_txt(path: &path::PathBuf) -> bool {
     RE_TXT.is_match(path.to_str().unwrap()) && fs::metadata(path).unwrap().is_file()
 }
 
+// directory of alias links, next to alias.exe
+pub fn alias_list_dir() -> path::PathBuf {
+    env::current_exe().unwrap().parent().unwrap().join(LISTDIR)
+}
+
+// find executables named <name> in %PATH%, in order of precedence
+pub fn which(name: &str) -> Vec<path::PathBuf> {
+    let exts: Vec<String> = env::var("PATHEXT")
+        .unwrap_or(".COM;.EXE;.BAT;.CMD".to_owned())
+        .split(';')
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_lowercase())
+        .collect();
+
+    let mut found = Vec::new();
+    if let Some(paths) = env::var_os("PATH") {
+        for dir in env::split_paths(&paths) {
+            for ext in &exts {
+                let candidate = dir.join(format!("{}{}", name, ext));
+                if candidate.is_file() && !found.contains(&candidate) {
+                    found.push(candidate);
+                }
+            }
+        }
+    }
+    found
+}
+
+// cmd.exe internal commands, they win over PATH
+pub fn is_cmd_builtin(name: &str) -> bool {
+    const BUILTINS: &[&str] = &[
+        "assoc", "break", "call", "cd", "chdir", "cls", "color", "copy", "date", "del", "dir", "echo",
+        "endlocal", "erase", "exit", "for", "ftype", "goto", "if", "md", "mkdir", "mklink", "move",
+        "path", "pause", "popd", "prompt", "pushd", "rd", "rem", "ren", "rename", "rmdir", "set",
+        "setlocal", "shift", "start", "time", "title", "type", "ver", "verify", "vol",
+    ];
+    BUILTINS.contains(&name.to_lowercase().as_str())
+}
+
+pub fn same_path(a: &path::Path, b: &path::Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
 pub fn cfg_path() -> io::Result<String> {
     const CFG_DIR: &'static str = ".alias-exe";
 
@@ -37,6 +84,18 @@ pub fn cfg_list_path() -> io::Result<String> {
     Ok(format!("{}\\{}", cfg_path()?, LISTDIR))
 }
 
+// mruby user library, *.rb
+#[cfg(feature = "mruby")]
+pub fn cfg_lib_path() -> io::Result<String> {
+    Ok(format!("{}\\lib", cfg_path()?))
+}
+
+// compiled ```ruby blocks
+#[cfg(feature = "mruby")]
+pub fn cfg_cache_path() -> io::Result<String> {
+    Ok(format!("{}\\cache", cfg_path()?))
+}
+
 
 fn home_path() -> io::Result<String> {
     const USERPROFILE: &'static str = "USERPROFILE";
@@ -1,8 +1,7 @@
 use std::{io, ops};
-use std::io::{Error, ErrorKind};
 use regex::Regex;
 
-use crate::lib::term;
+use crate::lib::diag;
 
 pub fn replace_all_func(
     re: &Regex,
@@ -22,7 +21,7 @@ pub fn replace_all_func(
         }
         let rep = frep(&m);
         if let Err(err) = rep {
-            return Err(Error::new(ErrorKind::InvalidData, format!("{}\n\n{}{}{}", format!("{}", err).trim(), &text[..start], term::ewrite(elm.as_str())?, &text[elm.end()..])));
+            return Err(diag::with_label(err, text, elm.range()));
         }
 
         s.push_str(&rep.unwrap());
@@ -52,7 +51,7 @@ pub fn replace_all_func_nested(
 
         let rep = frep(&re.captures(s2).unwrap());
         if let Err(err) = rep {
-            return Err(Error::new(ErrorKind::InvalidData, format!("{}\n\n{}{}{}", format!("{}", err).trim(), s1, term::ewrite(s2)?, s3)));
+            return Err(diag::with_label(err, &s, rng));
         }
 
         s = format!("{}{}{}", s1, &rep.unwrap(), s3);
@@ -77,18 +76,3 @@ fn nested_match(re: &Regex, text: &str) -> Option<ops::Range<usize>>
     }
     Some(rng)
 }
-
-pub fn partition_re<'a>(re: &Regex, text: &'a str) -> Option<(&'a str, &'a str, &'a str)> {
-    if let Some(caps) = re.captures(text) {
-        if let Some(elm) = caps.get(0) {
-            let start = elm.start();
-            let end = elm.end();
-            return Some((
-                &text[..start],
-                &text[start..end],
-                &text[end..]
-            ));
-        }
-    }
-    None
-}
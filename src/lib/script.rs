@@ -0,0 +1,234 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::io;
+
+use regex::Regex;
+
+use crate::lib::repl;
+use crate::lib::diag;
+use crate::lib::exec::{self, Definition, Source, Parsed};
+
+// alias body, to standalone script
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Sh,
+    Bash,
+    Cmd,
+    Ps1,
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Option<Format> {
+        match format {
+            "sh" => Some(Format::Sh),
+            "bash" => Some(Format::Bash),
+            "cmd" => Some(Format::Cmd),
+            "ps1" => Some(Format::Ps1),
+            _ => None,
+        }
+    }
+
+    pub fn ext(&self) -> &'static str {
+        match self {
+            Format::Sh | Format::Bash => "sh",
+            Format::Cmd => "cmd",
+            Format::Ps1 => "ps1",
+        }
+    }
+}
+
+struct Compiler {
+    format: Format,
+    lines: RefCell<Vec<String>>,
+    // args used, e.g. "$1"
+    args: RefCell<BTreeSet<String>>,
+    // @pushd depth
+    depth: Cell<usize>,
+    // count of nested $( ... ), for temp variables
+    nested: Cell<usize>,
+}
+
+pub fn compile(format: Format, def: &Definition) -> io::Result<String> {
+    let compiler = Compiler {
+        format,
+        lines: RefCell::new(Vec::new()),
+        args: RefCell::new(BTreeSet::new()),
+        depth: Cell::new(0),
+        nested: Cell::new(0),
+    };
+
+    exec::split_source_func(&def.body, |source| {
+        match source {
+            Source::Cmd(chunk) => {
+                compiler.cmd(&chunk.source.replace("^\n", ""))
+                    .map_err(|err| diag::locate(err, &def.file, def.file_line(chunk.line), chunk.source))
+            },
+            Source::Script(lang, chunk) => {
+                Err(diag::error(&format!("{} block can't be translated", lang))
+                    .locate(&def.file, def.file_line(chunk.line), chunk.source)
+                    .into_error())
+            },
+        }
+    })?;
+
+    let mut script = compiler.prologue();
+    script.append(&mut compiler.lines.into_inner());
+    script.push(String::new());
+    Ok(script.join(if format == Format::Cmd { "\r\n" } else { "\n" }))
+}
+
+impl Compiler {
+    fn cmd(&self, source: &str) -> io::Result<()> {
+        lazy_static! {
+            static ref RE_ARGS: Regex = Regex::new(exec::ARGS).unwrap();
+            static ref RE_ENV: Regex = Regex::new(r"%([A-Za-z_][A-Za-z0-9_]*)%").unwrap();
+        }
+
+        if let Some(start) = source.find("<%=") {
+            return Err(diag::error("nested mruby can't be translated")
+                .label(source, start..start + 3)
+                .into_error());
+        }
+        exec::validate_nested(source)?;
+
+        let source = repl::replace_all_func(&RE_ARGS, source, |caps| self.arg(caps.get(0).unwrap().as_str()))?;
+        let source = match self.format {
+            Format::Sh | Format::Bash => RE_ENV.replace_all(&source, "$${$1}").into_owned(),
+            Format::Cmd | Format::Ps1 => self.nested(&source)?,
+        };
+
+        let mut lines = self.lines.borrow_mut();
+        match exec::parse_cmd_type(&source)? {
+            Parsed::SetEnv(key, value) => lines.push(match self.format {
+                Format::Sh | Format::Bash => format!("export {}=\"{}\"", key, value.replace('"', "\\\"")),
+                Format::Cmd => format!("set \"{}={}\"", key, value),
+                Format::Ps1 => format!("$env:{} = [Environment]::ExpandEnvironmentVariables('{}')", key, value.replace('\'', "''")),
+            }),
+            Parsed::Pushd(path) => {
+                self.depth.set(self.depth.get() + 1);
+                lines.push(match self.format {
+                    Format::Sh => format!("_alias_dir{}=\"$PWD\"; cd \"{}\"", self.depth.get(), path.trim_matches('"')),
+                    Format::Bash => format!("pushd \"{}\" > /dev/null", path.trim_matches('"')),
+                    Format::Cmd => format!("pushd \"{}\" || exit /b", path.trim_matches('"')),
+                    Format::Ps1 => format!("Push-Location ([Environment]::ExpandEnvironmentVariables('{}'))", path.trim_matches('"').replace('\'', "''")),
+                });
+            },
+            Parsed::Popd() => {
+                if self.depth.get() == 0 {
+                    return Err(diag::error("@popd: directory stack empty")
+                        .hint("@popd needs a preceding @pushd")
+                        .into_error());
+                }
+                lines.push(match self.format {
+                    Format::Sh => format!("cd \"$_alias_dir{}\"", self.depth.get()),
+                    Format::Bash => "popd > /dev/null".to_owned(),
+                    Format::Cmd => "popd".to_owned(),
+                    Format::Ps1 => "Pop-Location".to_owned(),
+                });
+                self.depth.set(self.depth.get() - 1);
+            },
+            Parsed::Cmd(cmd) => match self.format {
+                Format::Sh | Format::Bash => lines.push(cmd.to_owned()),
+                Format::Cmd => lines.push(format!("{} || exit /b", cmd)),
+                Format::Ps1 => {
+                    lines.push(format!("cmd /c --% {}", cmd));
+                    lines.push("if ($LASTEXITCODE -ne 0) { exit $LASTEXITCODE }".to_owned());
+                },
+            },
+            Parsed::Script(..) => unreachable!(),
+            Parsed::Directive(line, key, _) => {
+                return Err(diag::error(&format!("{} can't be translated", key))
+                    .label(line, 0..key.len())
+                    .into_error());
+            },
+        }
+        Ok(())
+    }
+
+    fn arg(&self, arg: &str) -> io::Result<String> {
+        exec::parse_arg(arg, &[String::new()])?;
+        self.args.borrow_mut().insert(arg.to_owned());
+
+        let n = &arg[arg.len() - 1..];
+        Ok(match self.format {
+            Format::Sh | Format::Bash => match arg {
+                "\"$+\"" => "\"$*\"".to_owned(),
+                _ => arg.to_owned(),
+            },
+            Format::Cmd => match arg {
+                "$0" => "%~n0".to_owned(),
+                "$#" => "%ALIAS_ARGC%".to_owned(),
+                "\"$@\"" => "%*".to_owned(),
+                "\"$+\"" => "\"%*\"".to_owned(),
+                _ => format!("%~{}", n),
+            },
+            Format::Ps1 => match arg {
+                "$#" => "%ALIAS_ARGC%".to_owned(),
+                "\"$@\"" => "%ALIAS_AT%".to_owned(),
+                "\"$+\"" => "\"%ALIAS_PLUS%\"".to_owned(),
+                _ => format!("%ALIAS_{}%", n),
+            },
+        })
+    }
+
+    // precompute nested $( ... ) into temp variables
+    fn nested(&self, source: &str) -> io::Result<String> {
+        lazy_static! {
+            static ref RE_NESTED_CMD: Regex = Regex::new(r"(?ms)\$\((.*?)\)").unwrap();
+        }
+
+        repl::replace_all_func_nested(&RE_NESTED_CMD, source, |caps| {
+            let inner = caps.get(1).unwrap().as_str().trim();
+            self.nested.set(self.nested.get() + 1);
+            let n = self.nested.get();
+
+            self.lines.borrow_mut().push(match self.format {
+                Format::Cmd => format!("for /f \"usebackq delims=\" %%i in (`{}`) do set \"ALIAS_NESTED{}=%%i\"", inner, n),
+                _ => format!("$env:ALIAS_NESTED{} = (cmd /c \"{}\" | Out-String).Trim()", n, inner.replace('`', "``").replace('"', "`\"").replace('$', "`$")),
+            });
+            Ok(format!("%ALIAS_NESTED{}%", n))
+        })
+    }
+
+    fn prologue(&self) -> Vec<String> {
+        let args = self.args.borrow();
+
+        let mut lines = Vec::new();
+        match self.format {
+            Format::Sh => {
+                lines.push("#!/bin/sh".to_owned());
+                lines.push("set -e".to_owned());
+            },
+            Format::Bash => {
+                lines.push("#!/usr/bin/env bash".to_owned());
+                lines.push("set -e".to_owned());
+            },
+            Format::Cmd => {
+                lines.push("@echo off".to_owned());
+                lines.push("setlocal".to_owned());
+                if args.contains("$#") {
+                    lines.push("set ALIAS_ARGC=0".to_owned());
+                    lines.push("for %%x in (%*) do set /a ALIAS_ARGC+=1".to_owned());
+                }
+            },
+            Format::Ps1 => {
+                lines.push("$ErrorActionPreference = 'Stop'".to_owned());
+                for arg in args.iter() {
+                    lines.push(match arg.as_str() {
+                        "$0" => "$env:ALIAS_0 = $MyInvocation.MyCommand.Name".to_owned(),
+                        "$#" => "$env:ALIAS_ARGC = $args.Count".to_owned(),
+                        "\"$@\"" => "$env:ALIAS_AT = ($args | ForEach-Object { if (\"$_\" -match '\\s') { '\"' + $_ + '\"' } else { \"$_\" } }) -join ' '".to_owned(),
+                        "\"$+\"" => "$env:ALIAS_PLUS = $args -join ' '".to_owned(),
+                        _ => {
+                            let n: usize = arg[1..].parse().unwrap();
+                            format!("$env:ALIAS_{} = $args[{}]", n, n - 1)
+                        },
+                    });
+                }
+            },
+        }
+        lines.push(String::new());
+        lines
+    }
+}
@@ -0,0 +1,96 @@
+use std::{io, ops};
+use std::io::{Error, ErrorKind};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::lib::alias;
+use crate::lib::meta;
+use crate::lib::term;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Scope {
+    All,
+    Name,
+    Body,
+}
+
+pub struct Found {
+    pub alias_name: String,
+    // matched ranges in alias_name
+    pub name: Vec<ops::Range<usize>>,
+    // (<description>, <matched ranges>)
+    pub description: Option<(String, Vec<ops::Range<usize>>)>,
+    pub body: Vec<String>,
+    // (<0-based line in body>, <matched ranges>)
+    pub lines: Vec<(usize, Vec<ops::Range<usize>>)>,
+}
+
+// case insensitive regex, or characters in order if fuzzy, e.g. "gst" matches "git status"
+pub fn pattern(pattern: &str, fuzzy: bool) -> io::Result<Regex> {
+    let pattern = if fuzzy {
+        pattern.chars()
+            .filter(|x| !x.is_whitespace())
+            .map(|x| regex::escape(&x.to_string()))
+            .collect::<Vec<String>>()
+            .join(".*?")
+    } else {
+        pattern.to_owned()
+    };
+
+    match RegexBuilder::new(&pattern).case_insensitive(true).build() {
+        Ok(re) => Ok(re),
+        Err(err) => Err(Error::new(ErrorKind::InvalidInput, format!("{}: {}", term::ewrite("failed")?, err))),
+    }
+}
+
+pub fn search(re: &Regex, scope: Scope) -> io::Result<Vec<Found>> {
+    let ranges = |text: &str| -> Vec<ops::Range<usize>> {
+        re.find_iter(text).map(|m| m.range()).filter(|x| !x.is_empty()).collect()
+    };
+
+    let mut found = Vec::new();
+    for (key, value) in alias::list_iter()? {
+        let name = if scope != Scope::Body { ranges(&key) } else { Vec::new() };
+
+        let mut description = None;
+        if scope == Scope::All {
+            // broken one is skipped, as no description
+            if let Some(text) = meta::read(&key).ok().and_then(|x| x.description) {
+                let matched = ranges(&text);
+                if !matched.is_empty() {
+                    description = Some((text, matched));
+                }
+            }
+        }
+
+        let body: Vec<String> = value.lines().map(|x| x.to_owned()).collect();
+        let mut lines = Vec::new();
+        if scope != Scope::Name {
+            for (i, line) in body.iter().enumerate() {
+                let matched = ranges(line);
+                if !matched.is_empty() {
+                    lines.push((i, matched));
+                }
+            }
+        }
+
+        if !name.is_empty() || description.is_some() || !lines.is_empty() {
+            found.push(Found { alias_name: key, name, description, body, lines });
+        }
+    }
+    found.sort_by(|a, b| a.alias_name.cmp(&b.alias_name));
+    Ok(found)
+}
+
+// matched ranges through term::keywrite
+pub fn highlight(text: &str, ranges: &Vec<ops::Range<usize>>) -> io::Result<String> {
+    let mut s = String::new();
+    let mut cur = 0;
+    for range in ranges {
+        s.push_str(&text[cur..range.start]);
+        s.push_str(&term::keywrite(&text[range.clone()])?);
+        cur = range.end;
+    }
+    s.push_str(&text[cur..]);
+    Ok(s)
+}
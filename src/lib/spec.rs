@@ -0,0 +1,227 @@
+use std::{env, fs, process, thread};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::io::{self, Write, Error, ErrorKind};
+use std::process::{Command, Stdio};
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::lib::encode;
+use crate::lib::term;
+
+// <alias_name>.test.toml
+//
+//   [[case]]
+//   name = "greet"
+//   args = ["world"]
+//   env = { GREETING = "hello" }
+//   stdin = ""
+//   status = 0
+//   stdout = "hello world"     # exact
+//   stderr_regex = "^$"        # regex
+//   snapshot = true            # compare with <alias_name>.snap/<case>.stdout|stderr
+
+#[derive(Deserialize)]
+pub struct Spec {
+    #[serde(default)]
+    pub case: Vec<Case>,
+}
+
+#[derive(Deserialize)]
+pub struct Case {
+    pub name: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    pub stdin: Option<String>,
+    #[serde(default)]
+    pub status: i32,
+    pub stdout: Option<String>,
+    pub stdout_regex: Option<String>,
+    pub stderr: Option<String>,
+    pub stderr_regex: Option<String>,
+    #[serde(default)]
+    pub snapshot: bool,
+}
+
+pub fn spec_path(listdir: &str, alias_name: &str) -> String {
+    format!("{}/{}.test.toml", listdir, alias_name)
+}
+
+pub fn snap_path(listdir: &str, alias_name: &str) -> String {
+    format!("{}/{}.snap", listdir, alias_name)
+}
+
+pub fn read(spec_toml: &str) -> io::Result<Spec> {
+    let text = fs::read_to_string(spec_toml)?;
+    let spec: Spec = match toml::from_str(&text) {
+        Ok(spec) => spec,
+        Err(err) => return Err(Error::new(ErrorKind::InvalidData, format!("{}: {}: {}", term::ewrite("failed")?, spec_toml, err))),
+    };
+
+    // name of snapshot files in <alias_name>.snap
+    for case in &spec.case {
+        if case.name.is_empty() || case.name.contains(['/', '\\']) || case.name.starts_with('.') {
+            return Err(Error::new(ErrorKind::InvalidData, format!("{}: {}: illegal case name: {}", term::ewrite("failed")?, spec_toml, case.name)));
+        }
+    }
+    Ok(spec)
+}
+
+// -----
+
+pub struct Output {
+    pub status: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// alias.exe runs <alias_name> as a test case, instead of as `alias` command
+const TEST_ENV: &str = "ALIAS_EXE_TEST";
+
+// Some(alias_name) if this process is a test case started by run
+//
+// removed from env, nested `alias` in the case is the command as usual
+pub fn test_target() -> Option<String> {
+    let alias_name = env::var(TEST_ENV).ok()?;
+    env::remove_var(TEST_ENV);
+    Some(alias_name)
+}
+
+// run case by exec::run of alias.exe, in a temp working directory
+//
+// child process, as stdout and stderr of commands in alias are captured
+// not by alias link, which may be missing. runs are not recorded in meta
+pub fn run(alias_name: &str, case: &Case) -> io::Result<Output> {
+    let workdir = temp_dir()?;
+    let output = (|| {
+        let mut child = Command::new(env::current_exe()?)
+            .args(&case.args)
+            .env(TEST_ENV, alias_name)
+            .envs(&case.env)
+            .current_dir(&workdir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // write in thread, alias may fill stdout before reading all of stdin
+        let mut stdin = child.stdin.take().unwrap();
+        let text = case.stdin.clone().unwrap_or_default();
+        let writer = thread::spawn(move || {
+            // alias may exit without reading it, broken pipe is not an error
+            let _ = stdin.write_all(text.as_bytes());
+        });
+        let output = child.wait_with_output();
+        let _ = writer.join();
+        output
+    })();
+    // best-effort, result of case matters
+    let _ = fs::remove_dir_all(&workdir);
+    let output = output?;
+
+    Ok(Output {
+        status: output.status.code().unwrap_or(-1),
+        stdout: normalize(&encode::to_utf8_string(&output.stdout)),
+        stderr: normalize(&encode::to_utf8_string(&output.stderr)),
+    })
+}
+
+fn temp_dir() -> io::Result<std::path::PathBuf> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = env::temp_dir().join(format!("alias-exe-test-{}-{}", process::id(), COUNT.fetch_add(1, Ordering::SeqCst)));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn normalize(s: &str) -> String {
+    s.replace("\r\n", "\n").trim_end().to_owned()
+}
+
+// -----
+
+// check output, returns failure messages
+//
+// update: rewrite snapshots instead of comparing
+pub fn check(case: &Case, output: &Output, snap_dir: &str, update: bool) -> io::Result<Vec<String>> {
+    let mut failures = Vec::new();
+
+    if output.status != case.status {
+        failures.push(format!("status: expected {}, but {}", case.status, output.status));
+    }
+
+    for (stream, actual, exact, re) in [
+        ("stdout", &output.stdout, &case.stdout, &case.stdout_regex),
+        ("stderr", &output.stderr, &case.stderr, &case.stderr_regex),
+    ] {
+        if let Some(expected) = exact {
+            let expected = normalize(expected);
+            if &expected != actual {
+                failures.push(format!("{}:\n{}", stream, diff(&expected, actual)?));
+            }
+        }
+        if let Some(re) = re {
+            let re = match Regex::new(re) {
+                Ok(re) => re,
+                Err(err) => return Err(Error::new(ErrorKind::InvalidData, format!("{}: {}: {}_regex: {}", term::ewrite("failed")?, case.name, stream, err))),
+            };
+            if !re.is_match(actual) {
+                failures.push(format!("{}: does not match /{}/\n{}", stream, re, actual));
+            }
+        }
+
+        if case.snapshot {
+            let snap = format!("{}/{}.{}", snap_dir, case.name, stream);
+            if update {
+                fs::create_dir_all(snap_dir)?;
+                fs::write(&snap, actual)?;
+            } else if !Path::new(&snap).exists() {
+                failures.push(format!("{}: snapshot {} is not found, run with --update", stream, snap));
+            } else {
+                let expected = normalize(&fs::read_to_string(&snap)?);
+                if &expected != actual {
+                    failures.push(format!("{}: snapshot differs\n{}", stream, diff(&expected, actual)?));
+                }
+            }
+        }
+    }
+
+    Ok(failures)
+}
+
+// line diff, by longest common subsequence
+fn diff(expected: &str, actual: &str) -> io::Result<String> {
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut s = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            s.push_str(&format!("  {}\n", a[i]));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            s.push_str(&format!("{}\n", term::keywrite(&format!("+ {}", b[j]))?));
+            j += 1;
+        } else {
+            s.push_str(&format!("{}\n", term::ewrite(&format!("- {}", a[i]))?));
+            i += 1;
+        }
+    }
+    Ok(s.trim_end().to_owned())
+}
@@ -1,13 +1,35 @@
-use std::io::{self, Write};
+use std::{env, io::{self, Write}};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use termcolor::{Color, ColorChoice, ColorSpec, WriteColor, BufferWriter};
+use termcolor::{ColorChoice, ColorSpec, WriteColor, BufferWriter};
 use atty::Stream;
 
 use crate::lib::encode;
 
+pub use termcolor::Color;
+
+static COLOR: AtomicBool = AtomicBool::new(true);
+
+// disable color regardless of tty, e.g. for JSON output
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+// columns of terminal, 80 if unknown
+pub fn width() -> usize {
+    if let Some((terminal_size::Width(w), _)) = terminal_size::terminal_size() {
+        return w as usize;
+    }
+    env::var("COLUMNS").ok().and_then(|x| x.parse().ok()).unwrap_or(80)
+}
+
 pub fn keywrite(text: &str) -> io::Result<String> {
-    if !atty::is(Stream::Stdout) {
+    colorwrite(text, Color::Green)
+}
+
+pub fn colorwrite(text: &str, fg: Color) -> io::Result<String> {
+    if !COLOR.load(Ordering::Relaxed) || !atty::is(Stream::Stdout) {
         return Ok(encode::to_utf8_string(text.as_bytes()));
     }
 
@@ -16,13 +38,13 @@ pub fn keywrite(text: &str) -> io::Result<String> {
     }
 
     let mut w = WRITER.lock().unwrap();
-    w.set_fg_color(Color::Green)?;
+    w.set_fg_color(fg)?;
 
     w.write(text)
 }
 
 pub fn ewrite(text: &str) -> io::Result<String> {
-    if !atty::is(Stream::Stderr) {
+    if !COLOR.load(Ordering::Relaxed) || !atty::is(Stream::Stderr) {
         return Ok(encode::to_utf8_string(text.as_bytes()));
     }
 
@@ -12,6 +12,8 @@ fn main() {
 
     if lib::path::self_is_symlink() {
         do_exec::run(&args);
+    } else if let Some(alias_name) = lib::spec::test_target() {
+        do_exec::run_test(&alias_name, &args);
     } else {
         do_alias::run(&args);
     }